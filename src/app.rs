@@ -1,11 +1,85 @@
-use std::{collections::HashMap, sync::mpsc::Sender};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::time::{Duration, Instant};
 
-use crate::network::IoEvent;
-use std::ops::Deref;
+use crate::attachment::{self, Attachment};
+use crate::completion::{self, ATTACH_COMMAND};
+use crate::config::Config;
+use crate::editor::InputEditor;
+use crate::error::Error;
+use crate::keymap::Keymap;
+use crate::network::{IoEvent, LoadPreviewData, SendTypingData};
+use crate::notify::{Notification, Notifier};
+use crate::store::MessageStore;
+use crate::ui::image::{ImagePlacement, ImageProtocol, Preview};
+use crate::update::Effect;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tui::layout::Rect;
 
+/// Amount of messages loaded from the history at once
+pub const HISTORY_PAGE_SIZE: usize = 100;
+/// How long a typing indicator is shown without being refreshed
+pub const TYPING_TIMEOUT: Duration = Duration::from_secs(15);
+/// Interval at which typing started is repeated while the user keeps typing
+pub const TYPING_REFRESH: Duration = Duration::from_secs(10);
+/// Time without edits after which typing stopped is sent
+pub const TYPING_IDLE: Duration = Duration::from_secs(5);
+/// How long a notice stays in the status bar
+pub const NOTICE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Amount of errors kept in the error log
+const MAX_ERROR_LOG: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum View {
+    Accounts,
     Contacts,
-    Chat
+    Chat,
+    /// Selecting a message in the chat to act on it
+    Messages,
+    /// The errors reported in this session
+    Errors,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoticeKind {
+    Info,
+    Error,
+}
+
+/// A short message shown in the status bar until it expires
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notice {
+    pub text: String,
+    pub kind: NoticeKind,
+    pub expires: Instant,
+}
+
+/// An error in the error log
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoggedError {
+    pub time: DateTime<Local>,
+    pub message: String,
+}
+
+/// State of the connection to the signald socket
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// The socket was lost, the next attempt to reconnect is at `retry_at`
+    Disconnected { retry_at: Instant },
+}
+
+/// What the last frame showed in the chat pane.
+/// Messages take several rows, so scrolling by pages is based on what was actually shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChatFrame {
+    /// Amount of messages shown, at least partly
+    pub shown: usize,
+    /// Largest scroll that still fills the pane
+    pub max_scroll: usize,
 }
 
 pub struct Point {
@@ -13,125 +87,1152 @@ pub struct Point {
     pub y: u16,
 }
 
+#[derive(Clone)]
+pub enum ConversationKind {
+    Contact(Contact),
+    Group(Group),
+}
+
 #[derive(Clone)]
 pub struct Conversation {
-    pub contact: Contact,
+    /// Number of the contact or id of the group
+    pub id: String,
+    pub kind: ConversationKind,
     pub messages: Vec<Message>,
+    /// Whether older messages can still be loaded from the history
+    pub has_more_history: bool,
+    /// Amount of received messages since the conversation was last selected
+    pub unread: usize,
+    /// Timestamp of the most recent message
+    pub last_activity: i64,
+    /// Amount of messages scrolled up from the bottom, 0 follows new messages
+    pub scroll: usize,
+    /// Amount of messages received below the visible ones while scrolled up
+    pub new_below: usize,
+    /// Unsent input, kept while another conversation is selected
+    pub draft: String,
+    /// Current reactions, keyed by the author and timestamp of the message they react to
+    pub reactions: HashMap<(String, i64), Vec<Reaction>>,
+    /// Members that are typing, with the time their indicator expires
+    pub typing: HashMap<String, Instant>,
 }
 impl Conversation {
     pub fn new(contact: Contact) -> Self {
+        Conversation::with_kind(contact.number.clone(), ConversationKind::Contact(contact))
+    }
+
+    pub fn new_group(group: Group) -> Self {
+        Conversation::with_kind(group.id.clone(), ConversationKind::Group(group))
+    }
+
+    fn with_kind(id: String, kind: ConversationKind) -> Self {
         Self {
-            contact,
+            id,
+            kind,
             messages: Vec::new(),
+            has_more_history: false,
+            unread: 0,
+            last_activity: 0,
+            scroll: 0,
+            new_below: 0,
+            draft: String::new(),
+            reactions: HashMap::new(),
+            typing: HashMap::new(),
+        }
+    }
+
+    /// Numbers of the members currently typing, sorted
+    pub fn typing_members(&self) -> Vec<&str> {
+        let now = Instant::now();
+        let mut members: Vec<&str> = self.typing.iter()
+            .filter(|(_, expires)| **expires > now)
+            .map(|(number, _)| number.as_str())
+            .collect();
+        members.sort();
+        members
+    }
+
+    /// Apply a reaction, replacing the earlier reaction of its sender to the same message
+    pub fn apply_reaction(&mut self, reaction: Reaction) {
+        let key = (reaction.target_author.clone(), reaction.target_timestamp);
+        let reactions = self.reactions.entry(key).or_insert_with(Vec::new);
+        reactions.retain(|r| r.sender != reaction.sender);
+        if !reaction.remove {
+            reactions.push(reaction);
+        }
+    }
+
+    /// The reactions to a message as emoji and count, in the order they were first used
+    pub fn reaction_counts(&self, message: &Message) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        let key = (message.sender.clone(), message.timestamp);
+        for reaction in self.reactions.get(&key).into_iter().flatten() {
+            match counts.iter_mut().find(|(e, _)| *e == reaction.emoji) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((reaction.emoji.clone(), 1)),
+            }
+        }
+        counts
+    }
+
+    /// The emoji `sender` reacted with to a message
+    pub fn reaction_of(&self, message: &Message, sender: &str) -> Option<&str> {
+        self.reactions.get(&(message.sender.clone(), message.timestamp))?
+            .iter()
+            .find(|r| r.sender == sender)
+            .map(|r| r.emoji.as_str())
+    }
+
+    /// Text of the messages sent by `username`, oldest first
+    pub fn sent_messages(&self, username: &str) -> Vec<&str> {
+        self.messages.iter()
+            .filter(|m| m.sender == username && !m.message.is_empty())
+            .map(|m| m.message.as_str())
+            .collect()
+    }
+
+    pub fn is_group(&self) -> bool {
+        match self.kind {
+            ConversationKind::Group(_) => true,
+            ConversationKind::Contact(_) => false,
+        }
+    }
+
+    /// The name shown for this conversation
+    pub fn title(&self) -> String {
+        match &self.kind {
+            ConversationKind::Contact(c) => c.display_name(),
+            ConversationKind::Group(g) => g.name.clone()
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| "Unnamed group".to_string()),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Contact {
     pub number: String,
     pub name: Option<String>,
     pub color: Option<String>,
 }
+impl Contact {
+    /// The name of the contact, or its number when it has none
+    pub fn display_name(&self) -> String {
+        self.name.clone()
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| self.number.clone())
+    }
+}
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub id: String,
+    pub name: Option<String>,
+    /// Numbers of the group members
+    pub members: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message {
     pub sender: String,
     pub receiver: String,
     pub timestamp: i64,
     pub message: String,
+    #[serde(default)]
+    pub status: DeliveryStatus,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// The message this one replies to
+    #[serde(default)]
+    pub quote: Option<Quote>,
+}
+
+/// A quoted message, shown above a reply
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    /// Timestamp of the quoted message
+    pub id: i64,
+    pub author: String,
+    pub text: String,
+}
+
+impl Quote {
+    pub fn of(message: &Message) -> Quote {
+        // Attachments without a text are quoted by their name
+        let text = match message.attachments.first() {
+            Some(a) if message.message.is_empty() => a.filename.clone(),
+            _ => message.message.clone(),
+        };
+        Quote {
+            id: message.timestamp,
+            author: message.sender.clone(),
+            text,
+        }
+    }
+}
+
+/// An emoji reaction to a message, or the removal of one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Reaction {
+    pub sender: String,
+    pub emoji: String,
+    /// Sender of the message reacted to
+    pub target_author: String,
+    /// Timestamp of the message reacted to
+    pub target_timestamp: i64,
+    #[serde(default)]
+    pub remove: bool,
+}
+
+/// Delivery state of a message, ordered by progress
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// Sending failed
+    Failed,
+    /// Sent by another user
+    Received,
+    /// Handed to signald, no response yet
+    Sending,
+    /// Accepted by the server
+    Sent,
+    /// Delivered to the recipient
+    Delivered,
+    /// Read by the recipient
+    Read,
+}
+
+impl Default for DeliveryStatus {
+    fn default() -> Self {
+        DeliveryStatus::Sent
+    }
 }
 
 pub struct App {
+    pub config: Config,
+    pub keymap: Keymap,
     pub loaded: bool,
 
     pub username: String,
+    pub connection: ConnectionState,
+    pub accounts: Vec<String>,
     pub contacts: Vec<Contact>,
+    pub groups: Vec<Group>,
     pub conversations: HashMap<String, Conversation>,
+    /// Ids of the conversations, in the order shown in the sidebar
+    pub conversation_list: Vec<String>,
+    pub store: Option<MessageStore>,
+    /// Drafts loaded from the store for conversations that don't exist yet
+    saved_drafts: HashMap<String, String>,
+    /// Ids of the conversations that don't notify
+    pub muted: HashSet<String>,
+    /// Notifies about incoming messages, no notifications are shown when `None`
+    notifier: Option<Box<dyn Notifier>>,
 
-    pub io_tx: Sender<IoEvent>,
+    pub io_tx: UnboundedSender<IoEvent>,
 
     // Input
-    pub input_string: String,
-    pub input_position: usize,
+    pub input: InputEditor,
+    /// Position in the sent messages while recalling them, counted from the newest
+    pub input_history_index: Option<usize>,
+    /// Input that was being written before recalling sent messages
+    input_history_stash: String,
+    /// The message the input replies to
+    pub reply_to: Option<Quote>,
+    /// Conversation told the user is typing, and when typing started was last sent
+    typing_sent: Option<(String, Instant)>,
+    /// Time of the last edit of the input
+    last_input_edit: Instant,
     pub draw_cursor: bool,
     pub cursor_pos: Point,
+    /// Paths offered by the attachment picker
+    pub completions: Vec<String>,
+    /// Candidate of the attachment picker currently in the input
+    pub completion_index: Option<usize>,
+
+    // Chat
+    /// What the chat pane showed, set when drawing
+    pub chat_frame: ChatFrame,
+    /// How image previews are drawn, resolved from the config on startup
+    pub image_protocol: ImageProtocol,
+    /// Decoded image attachments by path, `None` when the file couldn't be decoded
+    pub image_previews: HashMap<String, Option<Preview>>,
+    /// Image attachments the network is decoding
+    pub pending_previews: HashSet<String>,
+    /// Images to draw with a graphics protocol after the frame, set when drawing
+    pub image_placements: Vec<ImagePlacement>,
+
+    // Account picker
+    pub selected_account_index: usize,
 
-    // Contact list
-    pub selected_contact_index: usize,
+    // Conversation list
+    pub selected_conversation_index: usize,
+    /// Index of the first conversation shown in the sidebar
+    pub sidebar_scroll: usize,
+
+    // Message selection
+    /// Index of the selected message in the current conversation
+    pub selected_message: Option<usize>,
+    /// Index of the highlighted emoji while picking a reaction
+    pub reaction_picker: Option<usize>,
 
     // View
     pub focused_view: View,
+    /// Shown in the status bar, replaced by newer notices
+    pub notice: Option<Notice>,
+    /// Errors reported in this session, oldest first
+    pub error_log: Vec<LoggedError>,
+    /// The view to return to when closing the error log
+    view_before_errors: View,
+    /// Whether the conversation list is shown next to the chat
+    pub sidebar_visible: bool,
+    /// Areas of the panes in the last frame, used to handle mouse clicks
+    pub sidebar_area: Option<Rect>,
+    pub chat_area: Rect,
+    pub input_area: Rect,
 }
 
 impl App {
-    pub fn new(io_tx: Sender<IoEvent>) -> Self {
+    pub fn new(io_tx: UnboundedSender<IoEvent>) -> Self {
+        App::with_config(io_tx, Config::default())
+    }
+
+    pub fn with_config(io_tx: UnboundedSender<IoEvent>, config: Config) -> Self {
+        let image_protocol = config.images.protocol.detect();
+        let muted = config.notifications.muted.iter().cloned().collect();
+        let sidebar_visible = config.show_sidebar;
         Self {
+            keymap: Keymap::from_config(&config.keys).unwrap_or_default(),
+            config,
             loaded: false,
             username: "".to_string(),
-            input_string: String::new(),
-            input_position: 0,
+            connection: ConnectionState::Connecting,
+            accounts: Vec::new(),
+            input: InputEditor::new(),
+            input_history_index: None,
+            input_history_stash: String::new(),
+            reply_to: None,
+            typing_sent: None,
+            last_input_edit: Instant::now(),
             draw_cursor: false,
             cursor_pos: Point { x: 0, y: 0 },
+            completions: Vec::new(),
+            completion_index: None,
+            chat_frame: ChatFrame::default(),
+            image_protocol,
+            image_previews: HashMap::new(),
+            pending_previews: HashSet::new(),
+            image_placements: Vec::new(),
             contacts: Vec::new(),
+            groups: Vec::new(),
             conversations: HashMap::new(),
-            selected_contact_index: 0,
+            conversation_list: Vec::new(),
+            store: None,
+            saved_drafts: HashMap::new(),
+            muted,
+            notifier: None,
+            selected_account_index: 0,
+            selected_conversation_index: 0,
+            sidebar_scroll: 0,
+            selected_message: None,
+            reaction_picker: None,
             io_tx,
             focused_view: View::Contacts,
+            notice: None,
+            error_log: Vec::new(),
+            view_before_errors: View::Contacts,
+            sidebar_visible,
+            sidebar_area: None,
+            chat_area: Rect::default(),
+            input_area: Rect::default(),
+        }
+    }
+
+    pub fn set_notifier(&mut self, notifier: Box<dyn Notifier>) {
+        self.notifier = Some(notifier);
+    }
+
+    /// Show a notification, reporting when it fails. Nothing happens without a notifier.
+    pub fn notify(&mut self, notification: &Notification) {
+        let result = match &mut self.notifier {
+            Some(notifier) => notifier.notify(notification),
+            None => return,
+        };
+        if let Err(e) = result {
+            self.report(Error::Io("show a notification".to_string(), e));
+        }
+    }
+
+    /// Show the account picker for the given accounts
+    pub fn pick_account(&mut self, accounts: Vec<String>) {
+        self.accounts = accounts;
+        self.selected_account_index = 0;
+        self.focused_view = View::Accounts;
+    }
+
+    pub fn select_account(&mut self, account_index: usize) {
+        if account_index < self.accounts.len() {
+            self.selected_account_index = account_index;
+        }
+    }
+
+    pub fn get_selected_account(&self) -> Option<String> {
+        self.accounts.get(self.selected_account_index).cloned()
+    }
+
+    /// Move the focus to the next pane
+    pub fn cycle_focus(&mut self) {
+        self.focused_view = match self.focused_view {
+            View::Accounts => View::Accounts,
+            View::Contacts => View::Chat,
+            View::Chat => View::Contacts,
+            View::Messages => View::Chat,
+            View::Errors => View::Errors,
+        };
+        self.selected_message = None;
+        self.reaction_picker = None;
+    }
+
+    /// Move the focus to a pane, leaving the message selection
+    pub fn focus(&mut self, view: View) {
+        if view != self.focused_view {
+            self.focused_view = view;
+            self.selected_message = None;
+            self.reaction_picker = None;
+        }
+    }
+
+    /// Show or hide the conversation list. It is still shown while it has the focus.
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar_visible = !self.sidebar_visible;
+        if !self.sidebar_visible && self.focused_view == View::Contacts {
+            self.focused_view = View::Chat;
         }
     }
 
     pub fn get_current_conversation(&mut self) -> Option<&mut Conversation> {
-        let contact = self.get_selected_contact();
-        if contact.is_none() { return None; }
+        let id = self.get_selected_conversation_id()?;
+        self.get_conversation(id)
+    }
 
-        let contact_num = contact.unwrap().number.clone();
-        self.get_conversation(contact_num)
+    pub fn get_conversation(&mut self, id: String) -> Option<&mut Conversation> {
+        self.conversations.get_mut(&id)
     }
 
-    pub fn get_conversation(&mut self, contact: String) -> Option<&mut Conversation> {
-        self.conversations.get_mut(&contact)
+    pub fn get_selected_conversation_id(&self) -> Option<String> {
+        self.conversation_list.get(self.selected_conversation_index).cloned()
     }
 
-    pub fn get_selected_contact(&mut self) -> Option<Contact> {
-        let con = self.contacts.get(self.selected_contact_index);
-        if con.is_none() { return None; }
-        return Some((*con.unwrap()).clone());
+    /// Find a contact by number
+    pub fn get_contact(&self, number: &str) -> Option<&Contact> {
+        self.contacts.iter().find(|c| c.number == number)
     }
 
-    pub fn update_contacts(&mut self, mut contacts: Vec<Contact>) {
-        contacts.iter_mut().for_each(|c| {
+    pub fn update_contacts(&mut self, mut contacts: Vec<Contact>) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        for c in contacts.iter_mut() {
             // If the contact is yourself, set the name
             if c.number == self.username {
-                if c.name.is_none() || c.name.clone().unwrap().is_empty() {
+                if c.name.as_deref().map_or(true, str::is_empty) {
                     c.name = Some("Me".to_string());
                 }
             }
 
             // Add a new conversation if it doesn't exist yet
-            if !self.conversations.contains_key(c.number.clone().as_str()) {
-                self.add_conversation((*c).clone());
+            if !self.conversations.contains_key(c.number.as_str()) {
+                effects.push(self.insert_conversation(Conversation::new(c.clone())));
             }
-        });
+        }
 
         self.contacts = contacts;
+        effects.extend(self.update_conversation_list());
+        effects
+    }
+
+    pub fn update_groups(&mut self, groups: Vec<Group>) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        for group in groups.iter() {
+            effects.extend(self.update_group(group.clone()));
+        }
+        self.groups = groups;
+        effects.extend(self.update_conversation_list());
+        effects
+    }
+
+    /// Add a group conversation or update the name and members of an existing one
+    pub fn update_group(&mut self, group: Group) -> Vec<Effect> {
+        let mut effects = Vec::new();
+        if let Some(conv) = self.conversations.get_mut(&group.id) {
+            conv.kind = ConversationKind::Group(group.clone());
+        } else {
+            effects.push(self.insert_conversation(Conversation::new_group(group.clone())));
+        }
+
+        match self.groups.iter_mut().find(|g| g.id == group.id) {
+            Some(g) => *g = group,
+            None => {
+                self.groups.push(group);
+                effects.extend(self.update_conversation_list());
+            }
+        }
+        effects
+    }
+
+    /// Rebuild the sidebar order, most recently active first.
+    /// Conversations without messages follow, contacts before groups.
+    pub fn update_conversation_list(&mut self) -> Vec<Effect> {
+        let selected = self.get_selected_conversation_id();
+
+        let mut list: Vec<String> = self.contacts.iter().map(|c| c.number.clone())
+            .chain(self.groups.iter().map(|g| g.id.clone()))
+            .collect();
+        let conversations = &self.conversations;
+        list.sort_by_key(|id| {
+            std::cmp::Reverse(conversations.get(id).map_or(0, |c| c.last_activity))
+        });
+        self.conversation_list = list;
+
+        // Keep the same conversation selected
+        if let Some(id) = &selected {
+            if let Some(index) = self.conversation_list.iter().position(|c| c == id) {
+                self.selected_conversation_index = index;
+            }
+        }
+
+        // The input belongs to the selected conversation
+        let mut effects = Vec::new();
+        let now_selected = self.get_selected_conversation_id();
+        if selected != now_selected {
+            effects.extend(self.typing_stopped().map(Effect::Send));
+            self.switch_draft(selected, now_selected);
+            effects.push(Effect::SaveDrafts);
+        }
+        effects
+    }
+
+    /// Add a new conversation with its saved draft, returns the effect loading its history
+    fn insert_conversation(&mut self, mut conv: Conversation) -> Effect {
+        let id = conv.id.clone();
+        if let Some(draft) = self.saved_drafts.remove(&id) {
+            conv.draft = draft;
+        }
+        self.conversations.insert(id.clone(), conv);
+        Effect::LoadHistory(id)
+    }
+
+    /// Load the reactions and the newest messages of a conversation from the history
+    pub fn load_history(&mut self, id: String) {
+        let reactions = self.with_store("load reactions", |s| s.load_reactions(&id));
+        if let Some(conv) = self.conversations.get_mut(&id) {
+            for reaction in reactions.unwrap_or_default() {
+                conv.apply_reaction(reaction);
+            }
+        }
+        self.load_older_messages(id);
+    }
+
+    /// Open the message history and drafts of the loaded account
+    pub fn open_store(&mut self) {
+        match MessageStore::open(self.config.data_dir.clone(), &self.username) {
+            Ok(store) => self.store = Some(store),
+            Err(e) => {
+                self.report(Error::Io("open the message history".to_string(), e));
+                return;
+            }
+        }
+        self.saved_drafts = self.with_store("load the drafts", |s| s.load_drafts()).unwrap_or_default();
+        let muted = self.with_store("load the muted conversations", |s| s.load_muted());
+        self.muted.extend(muted.unwrap_or_default());
+    }
+
+    /// Run an operation on the store, reporting when it fails. Returns `None` without a store.
+    pub fn with_store<T, F>(&mut self, what: &str, operation: F) -> Option<T>
+        where F: FnOnce(&MessageStore) -> io::Result<T>,
+    {
+        let result = operation(self.store.as_ref()?);
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report(Error::Io(what.to_string(), e));
+                None
+            }
+        }
+    }
+
+    /// Mute or unmute the notifications of a conversation
+    pub fn toggle_mute(&mut self, conversation: &str) {
+        if !self.muted.remove(conversation) {
+            self.muted.insert(conversation.to_string());
+        }
+        let mut muted: Vec<String> = self.muted.iter().cloned().collect();
+        muted.sort();
+        self.with_store("save the muted conversations", |s| s.save_muted(&muted));
+    }
+
+    /// The notification for an incoming message, `None` when it shouldn't notify.
+    /// Messages in the selected conversation never notify, also when the terminal is in the
+    /// background: crossterm doesn't report whether the terminal has focus.
+    pub fn notification_for(&self, conversation: &str, message: &Message) -> Option<Notification> {
+        let config = &self.config.notifications;
+        if !config.enabled
+            || message.sender == self.username
+            || self.muted.contains(conversation)
+            || self.get_selected_conversation_id().as_deref() == Some(conversation) {
+            return None;
+        }
+
+        let sender = self.get_contact(&message.sender)
+            .map_or(message.sender.clone(), |c| c.display_name());
+        let title = match self.conversations.get(conversation) {
+            Some(conv) if conv.is_group() => format!("{} in {}", sender, conv.title()),
+            _ => sender,
+        };
+        let body = match message.attachments.first() {
+            _ if !config.show_body => "New message".to_string(),
+            Some(a) if message.message.is_empty() => format!("📎 {}", a.filename),
+            _ => message.message.clone(),
+        };
+
+        Some(Notification {
+            conversation: conversation.to_string(),
+            title,
+            body,
+        })
+    }
+
+    /// Persist the drafts of all conversations, including the current input
+    pub fn save_drafts(&mut self) {
+        let mut drafts: HashMap<String, String> = self.conversations.values()
+            .filter(|c| !c.draft.is_empty())
+            .map(|c| (c.id.clone(), c.draft.clone()))
+            .collect();
+        if let Some(id) = self.get_selected_conversation_id() {
+            drafts.remove(&id);
+            if !self.input.is_empty() {
+                drafts.insert(id, self.input.text().to_string());
+            }
+        }
+        // Keep drafts of conversations that weren't loaded this session
+        for (id, draft) in self.saved_drafts.iter() {
+            drafts.entry(id.clone()).or_insert_with(|| draft.clone());
+        }
+
+        self.with_store("save the drafts", |s| s.save_drafts(&drafts));
+    }
+
+    /// Move the input into the draft of the previous conversation and
+    /// restore the draft of the newly selected one
+    fn switch_draft(&mut self, from: Option<String>, to: Option<String>) {
+        let text = self.input.take();
+        if let Some(conv) = from.and_then(|id| self.conversations.get_mut(&id)) {
+            conv.draft = text;
+        }
+
+        let draft = to.and_then(|id| self.conversations.get_mut(&id))
+            .map(|c| std::mem::replace(&mut c.draft, String::new()))
+            .unwrap_or_default();
+        self.input = InputEditor::with_text(draft);
+        self.input_history_index = None;
+        self.reply_to = None;
+    }
+
+    /// Show or hide the typing indicator of a member of a conversation
+    pub fn set_typing(&mut self, conversation: &str, sender: &str, started: bool) {
+        if sender == self.username {
+            return;
+        }
+        if let Some(conv) = self.conversations.get_mut(conversation) {
+            if started {
+                conv.typing.insert(sender.to_string(), Instant::now() + TYPING_TIMEOUT);
+            } else {
+                conv.typing.remove(sender);
+            }
+        }
+    }
+
+    /// Tell the current conversation the user is typing after an edit of the input.
+    /// Typing started is only repeated every `TYPING_REFRESH`, and typing stopped
+    /// is sent right away when the input is cleared.
+    pub fn input_edited(&mut self) {
+        self.last_input_edit = Instant::now();
+        if self.input.is_empty() {
+            self.stop_typing();
+            return;
+        }
+        if !self.config.send_typing {
+            return;
+        }
+
+        let id = match self.get_selected_conversation_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let refresh = match &self.typing_sent {
+            Some((conversation, sent)) => *conversation != id || sent.elapsed() >= TYPING_REFRESH,
+            None => true,
+        };
+        if refresh {
+            self.typing_sent = Some((id.clone(), Instant::now()));
+            self.send_io(IoEvent::SendTyping(SendTypingData {
+                is_group: self.is_group(&id),
+                recipient: id,
+                started: true,
+            }));
+        }
+    }
+
+    pub fn show_info(&mut self, text: String) {
+        self.show_notice(text, NoticeKind::Info);
+    }
+
+    pub fn show_error(&mut self, text: String) {
+        self.show_notice(text, NoticeKind::Error);
+    }
+
+    fn show_notice(&mut self, text: String, kind: NoticeKind) {
+        self.notice = Some(Notice {
+            text,
+            kind,
+            expires: Instant::now() + NOTICE_TIMEOUT,
+        });
+    }
+
+    /// Add an error to the error log and show it in the status bar
+    pub fn report(&mut self, error: Error) {
+        let message = error.to_string();
+        if self.error_log.len() >= MAX_ERROR_LOG {
+            self.error_log.remove(0);
+        }
+        self.error_log.push(LoggedError {
+            time: Local::now(),
+            message: message.clone(),
+        });
+        self.show_error(message);
+    }
+
+    /// Pass an event to the network thread, reporting when it is gone
+    pub fn send_io(&mut self, event: IoEvent) {
+        if let Err(e) = self.io_tx.send(event) {
+            self.report(e.into());
+        }
+    }
+
+    /// Ask the network to decode image attachments that have no preview yet, once per image
+    pub fn request_previews(&mut self, paths: Vec<String>) {
+        for path in paths {
+            if self.image_previews.contains_key(&path) || !self.pending_previews.insert(path.clone()) {
+                continue;
+            }
+            self.send_io(IoEvent::LoadPreview(LoadPreviewData {
+                path,
+                max_columns: self.config.images.max_width,
+                max_rows: self.config.images.max_height,
+            }));
+        }
+    }
+
+    /// Open the error log, or go back to the previous view
+    pub fn toggle_error_log(&mut self) {
+        if self.focused_view == View::Errors {
+            self.focused_view = self.view_before_errors;
+        } else {
+            self.view_before_errors = self.focused_view;
+            self.focused_view = View::Errors;
+        }
+    }
+
+    /// Remove the notice once it expired, called periodically
+    pub fn expire_notice(&mut self) {
+        if self.notice.as_ref().map_or(false, |n| Instant::now() >= n.expires) {
+            self.notice = None;
+        }
+    }
+
+    /// Whether a conversation is a group, requests to groups are addressed by the group id
+    pub fn is_group(&self, id: &str) -> bool {
+        self.conversations.get(id).map_or(false, |c| c.is_group())
+    }
+
+    /// The sidebar title of a conversation, its id when it is unknown
+    pub fn conversation_title(&self, id: &str) -> String {
+        self.conversations.get(id).map_or(id.to_string(), |c| c.title())
+    }
+
+    /// Send typing stopped once the input was left alone for `TYPING_IDLE`, called periodically
+    pub fn check_typing(&mut self) {
+        if self.typing_sent.is_some() && self.last_input_edit.elapsed() >= TYPING_IDLE {
+            self.stop_typing();
+        }
+    }
+
+    /// Tell the conversation the user stopped typing, if it was told about typing
+    pub fn stop_typing(&mut self) {
+        if let Some(event) = self.typing_stopped() {
+            self.send_io(event);
+        }
+    }
+
+    /// The typing stopped event for the conversation told about typing, if any
+    fn typing_stopped(&mut self) -> Option<IoEvent> {
+        let (conversation, _) = self.typing_sent.take()?;
+        Some(IoEvent::SendTyping(SendTypingData {
+            is_group: self.is_group(&conversation),
+            recipient: conversation,
+            started: false,
+        }))
+    }
+
+    /// Forget that typing was sent, a sent message ends the typing indicator of the recipient
+    pub fn message_sent(&mut self) {
+        self.typing_sent = None;
+    }
+
+    /// Replace the input with the previous sent message of the current conversation
+    pub fn recall_previous_message(&mut self) {
+        let username = self.username.clone();
+        let sent: Vec<String> = match self.get_current_conversation() {
+            Some(c) => c.sent_messages(&username).iter().map(|m| m.to_string()).collect(),
+            None => return,
+        };
+
+        let index = match self.input_history_index {
+            None if sent.is_empty() => return,
+            None => {
+                self.input_history_stash = self.input.text().to_string();
+                0
+            }
+            Some(i) if i + 1 < sent.len() => i + 1,
+            Some(i) => i,
+        };
+        self.input_history_index = Some(index);
+        self.input.set_text(sent[sent.len() - 1 - index].clone());
+    }
+
+    /// Replace the input with the next sent message, or the stashed input after the newest one
+    pub fn recall_next_message(&mut self) {
+        let username = self.username.clone();
+        let sent: Vec<String> = match self.get_current_conversation() {
+            Some(c) => c.sent_messages(&username).iter().map(|m| m.to_string()).collect(),
+            None => return,
+        };
+
+        match self.input_history_index {
+            None => {}
+            Some(0) => {
+                self.input_history_index = None;
+                let stash = std::mem::replace(&mut self.input_history_stash, String::new());
+                self.input.set_text(stash);
+            }
+            Some(i) => {
+                self.input_history_index = Some(i - 1);
+                if let Some(text) = sent.len().checked_sub(i).and_then(|n| sent.get(n)) {
+                    self.input.set_text(text.clone());
+                }
+            }
+        }
+    }
+
+    /// Complete the path of an `/attach` command in the input.
+    /// With several candidates the common part is completed and the candidates are offered,
+    /// completing again cycles through them. Returns false when the input is not a command.
+    pub fn complete_input(&mut self) -> bool {
+        if !self.input.text().starts_with(ATTACH_COMMAND) {
+            return false;
+        }
+
+        if !self.completions.is_empty() {
+            let index = self.completion_index.map_or(0, |i| (i + 1) % self.completions.len());
+            self.completion_index = Some(index);
+            let text = format!("{}{}", ATTACH_COMMAND, self.completions[index]);
+            self.input.set_text(text);
+            return true;
+        }
+
+        let partial = self.input.text()[ATTACH_COMMAND.len()..].to_string();
+        let candidates = completion::complete_path(&partial);
+        let completed = completion::common_prefix(&candidates);
+        if completed.len() > partial.len() {
+            self.input.set_text(format!("{}{}", ATTACH_COMMAND, completed));
+        }
+        if candidates.len() > 1 {
+            self.completions = candidates;
+            self.completion_index = None;
+        }
+        true
     }
 
-    pub fn add_conversation(&mut self, contact: Contact) {
-        if !self.conversations.contains_key(contact.number.clone().as_str()) {
-            let conv = Conversation {
-                contact: contact.clone(),
-                messages: Vec::new(),
+    /// The attachment of the selected message,
+    /// or of the newest message in view of the current conversation
+    pub fn selected_attachment(&mut self) -> Option<Attachment> {
+        if self.selected_message.is_some() {
+            return self.get_selected_message()?.attachments.last().cloned();
+        }
+        let conv = self.get_current_conversation()?;
+        let end = conv.messages.len().saturating_sub(conv.scroll);
+        conv.messages[..end].iter()
+            .rev()
+            .find_map(|m| m.attachments.last())
+            .cloned()
+    }
+
+    /// Open the selected attachment with the configured command
+    pub fn open_selected_attachment(&mut self) {
+        let attachment = match self.selected_attachment() {
+            Some(a) => a,
+            None => {
+                self.show_error("No attachment in view".to_string());
+                return;
+            }
+        };
+        if let Err(e) = attachment::open(&attachment, &self.config.open_command) {
+            self.report(Error::Io(format!("open {}", attachment.filename), e));
+        }
+    }
+
+    /// Copy the selected attachment to the download directory
+    pub fn save_selected_attachment(&mut self) {
+        let attachment = match self.selected_attachment() {
+            Some(a) => a,
+            None => {
+                self.show_error("No attachment in view".to_string());
+                return;
+            }
+        };
+        let dir = match self.config.download_dir.clone().or_else(dirs::download_dir) {
+            Some(d) => d,
+            None => {
+                self.show_error("No download directory configured".to_string());
+                return;
+            }
+        };
+        match attachment::save(&attachment, &dir) {
+            Ok(path) => self.show_info(format!("Saved to {}", path.display())),
+            Err(e) => self.report(Error::Io(format!("save {}", attachment.filename), e)),
+        }
+    }
+
+    /// Close the attachment picker
+    pub fn clear_completions(&mut self) {
+        self.completions.clear();
+        self.completion_index = None;
+    }
+
+    /// Add a message to a conversation, returns the effects saving it in the history
+    pub fn add_message(&mut self, conversation: String, message: Message) -> Vec<Effect> {
+        let mut effects = vec![Effect::SaveMessage {
+            conversation: conversation.clone(),
+            message: message.clone(),
+        }];
+
+        let selected = self.get_selected_conversation_id();
+        if let Some(conv) = self.conversations.get_mut(&conversation) {
+            if message.sender != self.username && selected.as_ref() != Some(&conversation) {
+                conv.unread += 1;
+            }
+            conv.last_activity = conv.last_activity.max(message.timestamp);
+            // A message ends the typing indicator of its sender
+            conv.typing.remove(&message.sender);
+            conv.messages.push(message);
+
+            // Keep the view in place when scrolled up
+            if conv.scroll > 0 {
+                conv.scroll += 1;
+                conv.new_below += 1;
+            }
+            effects.extend(self.update_conversation_list());
+        }
+        effects
+    }
+
+    /// Apply a reaction to a message of a conversation, returns the effect saving it
+    pub fn add_reaction(&mut self, conversation: String, reaction: Reaction) -> Effect {
+        if let Some(conv) = self.conversations.get_mut(&conversation) {
+            conv.apply_reaction(reaction.clone());
+        }
+        Effect::SaveReaction { conversation, reaction }
+    }
+
+    /// Start selecting messages, beginning with the newest one in view
+    pub fn select_messages(&mut self) {
+        let selected = match self.get_current_conversation() {
+            Some(c) if !c.messages.is_empty() => c.messages.len().saturating_sub(c.scroll + 1),
+            _ => return,
+        };
+        self.selected_message = Some(selected);
+        self.reaction_picker = None;
+        self.focused_view = View::Messages;
+    }
+
+    /// Move the message selection by `offset`, loading older history when moving past the first message.
+    /// The selected message is kept at the bottom of the chat.
+    pub fn move_message_selection(&mut self, offset: isize) {
+        let id = match self.get_selected_conversation_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let mut selected = match self.selected_message {
+            Some(s) => s,
+            None => return,
+        };
+
+        if offset < 0 && selected == 0 {
+            let loaded = self.conversations.get(&id).map_or(0, |c| c.messages.len());
+            self.load_older_messages(id.clone());
+            selected += self.conversations.get(&id).map_or(0, |c| c.messages.len()) - loaded;
+        }
+
+        if let Some(conv) = self.conversations.get_mut(&id) {
+            let last = conv.messages.len().saturating_sub(1);
+            let selected = if offset < 0 {
+                selected.saturating_sub(offset.abs() as usize)
+            } else {
+                (selected + offset as usize).min(last)
             };
+            self.selected_message = Some(selected);
+            conv.scroll = last - selected;
+            conv.new_below = conv.new_below.min(conv.scroll);
+        }
+    }
+
+    /// Reply to the selected message from the input
+    pub fn reply_to_selected(&mut self) {
+        if let Some(message) = self.get_selected_message() {
+            self.reply_to = Some(Quote::of(&message));
+            self.selected_message = None;
+            self.reaction_picker = None;
+            self.scroll_to_bottom();
+            self.focused_view = View::Chat;
+        }
+    }
 
-            self.conversations.insert(contact.number.clone(), conv);
+    /// The selected message of the current conversation
+    pub fn get_selected_message(&mut self) -> Option<Message> {
+        let index = self.selected_message?;
+        self.get_current_conversation()?.messages.get(index).cloned()
+    }
+
+    /// Update the status of an own message, a status never moves backwards.
+    /// Returns the effect saving the new status, `None` when no message changed.
+    pub fn update_message_status(&mut self, timestamp: i64, status: DeliveryStatus) -> Option<Effect> {
+        let username = self.username.clone();
+        let found = self.conversations.values_mut()
+            .filter_map(|conv| {
+                let message = conv.messages.iter_mut()
+                    .rev()
+                    .find(|m| m.timestamp == timestamp && m.sender == username)?;
+                Some((conv.id.clone(), message))
+            })
+            .next();
+
+        let (conversation, message) = found?;
+        if status == DeliveryStatus::Failed || status > message.status {
+            message.status = status;
+            Some(Effect::SaveStatus { conversation, timestamp, status })
+        } else {
+            None
+        }
+    }
+
+    /// Scroll the current conversation up by `amount` messages,
+    /// loading older history when reaching the top
+    pub fn scroll_up(&mut self, amount: usize) {
+        let id = match self.get_selected_conversation_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let loaded = self.conversations.get(&id).map_or(0, |c| c.messages.len());
+
+        let needs_history = self.conversations.get(&id).map_or(false, |c| {
+            c.has_more_history && c.scroll + amount > self.chat_frame.max_scroll
+        });
+        if needs_history {
+            self.load_older_messages(id.clone());
+        }
+
+        if let Some(conv) = self.conversations.get_mut(&id) {
+            // Older messages are loaded above, the next frame measures the exact limit
+            self.chat_frame.max_scroll += conv.messages.len() - loaded;
+            conv.scroll = (conv.scroll + amount).min(self.chat_frame.max_scroll);
+        }
+    }
+
+    /// Scroll up by the messages shown in the last frame, keeping the top one in view
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.chat_frame.shown.saturating_sub(1).max(1));
+    }
+
+    /// Scroll down by the messages shown in the last frame, keeping the bottom one in view
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.chat_frame.shown.saturating_sub(1).max(1));
+    }
+
+    /// Scroll the current conversation down by `amount` messages
+    pub fn scroll_down(&mut self, amount: usize) {
+        if let Some(conv) = self.get_current_conversation() {
+            conv.scroll = conv.scroll.saturating_sub(amount);
+            conv.new_below = conv.new_below.min(conv.scroll);
         }
     }
 
-    pub fn select_conversation(&mut self, contact_index: usize) {
+    /// Scroll to the oldest loaded message
+    pub fn scroll_to_top(&mut self) {
+        let amount = self.get_current_conversation().map_or(0, |c| c.messages.len());
+        self.scroll_up(amount);
+    }
+
+    /// Scroll to the newest message and follow new messages again
+    pub fn scroll_to_bottom(&mut self) {
+        if let Some(conv) = self.get_current_conversation() {
+            conv.scroll = 0;
+            conv.new_below = 0;
+        }
+    }
+
+    /// Load the previous page of messages of a conversation from the history
+    pub fn load_older_messages(&mut self, conversation: String) {
+        let loaded = match self.conversations.get(&conversation) {
+            Some(c) => c.messages.len(),
+            None => return,
+        };
+        let page = self.with_store("load the message history", |s| {
+            s.load_page(&conversation, loaded, HISTORY_PAGE_SIZE)
+        });
+
+        if let (Some(mut page), Some(conv)) = (page, self.conversations.get_mut(&conversation)) {
+            conv.has_more_history = page.len() == HISTORY_PAGE_SIZE;
+            if let Some(last) = page.last() {
+                conv.last_activity = conv.last_activity.max(last.timestamp);
+            }
+            page.append(&mut conv.messages);
+            conv.messages = page;
+        }
+    }
+
+    /// Scroll the sidebar, which shows `rows` conversations, so the selected conversation is in view
+    pub fn scroll_sidebar(&mut self, rows: usize) {
+        let max_scroll = self.conversation_list.len().saturating_sub(rows);
+        let selected = self.selected_conversation_index;
+        self.sidebar_scroll = self.sidebar_scroll.min(max_scroll).min(selected);
+        if rows > 0 && selected >= self.sidebar_scroll + rows {
+            self.sidebar_scroll = selected + 1 - rows;
+        }
+    }
+
+    pub fn select_conversation(&mut self, conversation_index: usize) {
         if self.loaded {
-            if contact_index >= 0 && contact_index < self.conversations.len() {
-                self.selected_contact_index = contact_index;
+            if conversation_index < self.conversation_list.len() {
+                let previous = self.get_selected_conversation_id();
+                self.selected_conversation_index = conversation_index;
+                let selected = self.get_selected_conversation_id();
+                // Drafts are saved right away, so they survive the terminal being closed
+                if previous != selected {
+                    self.stop_typing();
+                    self.switch_draft(previous, selected);
+                    self.save_drafts();
+                }
+
+                if let Some(conv) = self.get_current_conversation() {
+                    conv.unread = 0;
+                }
             }
         }
     }
@@ -0,0 +1,135 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+/// A file sent along with a message
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Name of the file as shown in the chat
+    pub filename: String,
+    pub content_type: Option<String>,
+    /// Size in bytes, `None` when unknown
+    pub size: Option<u64>,
+    /// Where the file is stored on this machine
+    pub path: Option<String>,
+}
+
+impl Attachment {
+    /// Describe a local file, the size is left unknown when the file can't be read
+    pub fn from_path(path: &Path) -> Attachment {
+        let filename = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        let size = fs::metadata(path).ok()
+            .filter(|m| m.is_file())
+            .map(|m| m.len());
+
+        Attachment {
+            filename,
+            content_type: content_type(path).map(|t| t.to_string()),
+            size,
+            path: Some(path.display().to_string()),
+        }
+    }
+
+    /// Short description of the attachment, e.g. "photo.jpg (image/jpeg, 1.2 MB)"
+    pub fn summary(&self) -> String {
+        let details: Vec<String> = self.content_type.iter().cloned()
+            .chain(self.size.map(human_size))
+            .collect();
+        if details.is_empty() {
+            self.filename.clone()
+        } else {
+            format!("{} ({})", self.filename, details.join(", "))
+        }
+    }
+}
+
+/// Open a received attachment with an external command, e.g. `xdg-open`.
+/// A `{}` in the command is replaced by the path, otherwise the path is appended.
+pub fn open(attachment: &Attachment, command: &str) -> io::Result<()> {
+    let path = stored_path(attachment)?;
+    let mut parts = command.split_whitespace();
+    let program = parts.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "No open command configured"))?;
+
+    let mut args: Vec<String> = parts.map(|p| p.to_string()).collect();
+    if args.iter().any(|a| a.contains("{}")) {
+        args = args.iter().map(|a| a.replace("{}", path)).collect();
+    } else {
+        args.push(path.to_string());
+    }
+
+    // Keep the command from drawing over the interface
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Copy a received attachment into `dir` under its own name.
+/// Returns the path of the copy, an existing file is never overwritten.
+pub fn save(attachment: &Attachment, dir: &Path) -> io::Result<PathBuf> {
+    let source = stored_path(attachment)?;
+    fs::create_dir_all(dir)?;
+
+    let name = Path::new(&attachment.filename);
+    let stem = name.file_stem().map_or("attachment".into(), |s| s.to_string_lossy());
+    let extension = name.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let mut target = dir.join(format!("{}{}", stem, extension));
+    let mut copy = 1;
+    while target.exists() {
+        target = dir.join(format!("{} ({}){}", stem, copy, extension));
+        copy += 1;
+    }
+
+    fs::copy(source, &target)?;
+    Ok(target)
+}
+
+fn stored_path(attachment: &Attachment) -> io::Result<&str> {
+    attachment.path.as_deref()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Attachment was not downloaded"))
+}
+
+/// Guess the mime type of a file from its extension
+pub fn content_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let content_type = match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "m4a" | "aac" => "audio/aac",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "txt" => "text/plain",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+/// Format a size in bytes for display
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit + 1 < UNITS.len() {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
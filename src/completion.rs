@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// Input prefix of the command that sends a file
+pub const ATTACH_COMMAND: &str = "/attach ";
+
+/// The path of an `/attach <path>` command, `None` for other input
+pub fn attach_path(input: &str) -> Option<PathBuf> {
+    if !input.starts_with(ATTACH_COMMAND) {
+        return None;
+    }
+    let path = input[ATTACH_COMMAND.len()..].trim();
+    if path.is_empty() {
+        return None;
+    }
+    Some(expand_home(path))
+}
+
+/// Replace a leading `~` by the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    if path == "~" || path.starts_with("~/") {
+        if let Some(home) = dirs::home_dir() {
+            return home.join(path[1..].trim_start_matches('/'));
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// Paths starting with `partial`, sorted. Directories end with a `/` so they can be completed further.
+/// Hidden files are only included when the partial file name starts with a dot.
+pub fn complete_path(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+    let search_dir = if dir.is_empty() { PathBuf::from(".") } else { expand_home(dir) };
+
+    let entries = match fs::read_dir(search_dir) {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.path().is_dir();
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// The longest prefix shared by all candidates
+pub fn common_prefix(candidates: &[String]) -> String {
+    let first = match candidates.first() {
+        Some(f) => f,
+        None => return String::new(),
+    };
+    let mut end = first.len();
+    for candidate in candidates[1..].iter() {
+        end = first.char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((i, _), _)| i.min(end));
+    }
+    first[..end].to_string()
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{App as ClapApp, Arg, ArgMatches};
+use serde::Deserialize;
+
+use crate::keymap::{Keymap, KeymapConfig};
+use crate::notify::NotificationConfig;
+use crate::ui::image::ImageProtocol;
+
+const APP_NAME: &str = "signald-tui";
+const CONFIG_FILE: &str = "config.toml";
+
+/// User configuration, read from `$XDG_CONFIG_HOME/signald-tui/config.toml`.
+/// Command line arguments take precedence over the config file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Path of the signald unix socket
+    pub socket_path: String,
+    /// Number of the account to use, e.g. "+32400000000"
+    pub account: Option<String>,
+    /// Directory for the message history, defaults to `$XDG_DATA_HOME/signald-tui`
+    pub data_dir: Option<PathBuf>,
+    /// Command that opens attachments, `{}` is replaced by the file path
+    pub open_command: String,
+    /// Directory attachments are saved to, defaults to the download dir
+    pub download_dir: Option<PathBuf>,
+    /// Inline previews of image attachments
+    pub images: ImageConfig,
+    /// Emoji offered when reacting to a message
+    pub reactions: Vec<String>,
+    /// Let others see when you are typing
+    pub send_typing: bool,
+    pub notifications: NotificationConfig,
+    /// Width of the conversation list in columns
+    pub sidebar_width: u16,
+    /// Show the conversation list on startup, see the `toggle-sidebar` action
+    pub show_sidebar: bool,
+    /// Key bindings per view, see `Keymap`
+    pub keys: KeymapConfig,
+}
+
+/// The `[images]` section of the config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    /// Show image attachments in the chat
+    pub enabled: bool,
+    pub protocol: ImageProtocol,
+    /// Maximum size of a preview in terminal cells
+    pub max_width: u16,
+    pub max_height: u16,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            protocol: ImageProtocol::Auto,
+            max_width: 40,
+            max_height: 12,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            socket_path: crate::common::SOCKET_PATH.to_string(),
+            account: None,
+            data_dir: None,
+            open_command: "xdg-open".to_string(),
+            download_dir: None,
+            images: ImageConfig::default(),
+            reactions: ["👍", "❤️", "😂", "😮", "😢", "🙏"].iter().map(|e| e.to_string()).collect(),
+            send_typing: true,
+            notifications: NotificationConfig::default(),
+            sidebar_width: 20,
+            show_sidebar: true,
+            keys: KeymapConfig::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Build the config from the command line arguments and the config file
+    pub fn load() -> Result<Config, String> {
+        let matches = Config::cli().get_matches();
+
+        let path = match matches.value_of("config") {
+            Some(p) => Some(PathBuf::from(p)),
+            None => Config::default_path(),
+        };
+        let mut config = match path {
+            Some(p) if p.exists() => Config::from_file(&p)?,
+            _ => Config::default(),
+        };
+
+        config.apply_args(&matches);
+
+        // Report invalid key bindings on startup
+        Keymap::from_config(&config.keys)
+            .map_err(|e| format!("Invalid key binding: {}", e))?;
+
+        Ok(config)
+    }
+
+    /// The default config path, `None` when no config directory exists
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join(APP_NAME).join(CONFIG_FILE))
+    }
+
+    pub fn from_file(path: &PathBuf) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config {}: {}", path.display(), e))?;
+        Config::parse(&contents)
+            .map_err(|e| format!("Could not parse config {}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    fn cli<'a, 'b>() -> ClapApp<'a, 'b> {
+        ClapApp::new(APP_NAME)
+            .version(env!("CARGO_PKG_VERSION"))
+            .about("A signal TUI using signald")
+            .arg(Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Path of the config file")
+                .takes_value(true))
+            .arg(Arg::with_name("socket")
+                .short("s")
+                .long("socket")
+                .value_name("PATH")
+                .help("Path of the signald socket")
+                .takes_value(true))
+            .arg(Arg::with_name("account")
+                .short("a")
+                .long("account")
+                .value_name("NUMBER")
+                .help("Number of the signald account to use")
+                .takes_value(true))
+    }
+
+    fn apply_args(&mut self, matches: &ArgMatches) {
+        if let Some(socket) = matches.value_of("socket") {
+            self.socket_path = socket.to_string();
+        }
+        if let Some(account) = matches.value_of("account") {
+            self.account = Some(account.to_string());
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Maximum amount of undo steps kept
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+enum EditKind {
+    Insert,
+    Other,
+}
+
+/// Text editor state of the chat input.
+///
+/// The cursor is a byte offset into `text` that always lies on a grapheme boundary,
+/// so multi-byte characters and combining sequences are moved over and deleted as a whole.
+#[derive(Clone, Default)]
+pub struct InputEditor {
+    text: String,
+    cursor: usize,
+    undo_stack: Vec<(String, usize)>,
+    last_edit: Option<EditKind>,
+}
+
+impl InputEditor {
+    pub fn new() -> Self {
+        InputEditor::default()
+    }
+
+    /// An editor containing `text` with the cursor at the end
+    pub fn with_text(text: String) -> Self {
+        InputEditor {
+            cursor: text.len(),
+            text,
+            ..InputEditor::default()
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte offset of the cursor
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replace the text and move the cursor to the end, this can be undone
+    pub fn set_text(&mut self, text: String) {
+        self.save_undo(EditKind::Other);
+        self.cursor = text.len();
+        self.text = text;
+    }
+
+    /// Take the text out of the editor, clearing it and its undo history
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        self.undo_stack.clear();
+        self.last_edit = None;
+        std::mem::replace(&mut self.text, String::new())
+    }
+
+    pub fn insert(&mut self, c: char) {
+        // Typing a word is undone at once
+        let kind = if c.is_whitespace() { EditKind::Other } else { EditKind::Insert };
+        self.save_undo(kind);
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_newline(&mut self) {
+        self.save_undo(EditKind::Other);
+        self.text.insert(self.cursor, '\n');
+        self.cursor += 1;
+    }
+
+    pub fn delete_backward(&mut self) {
+        let start = self.previous_boundary(self.cursor);
+        self.delete_range(start, self.cursor);
+    }
+
+    pub fn delete_forward(&mut self) {
+        let end = self.next_boundary(self.cursor);
+        self.delete_range(self.cursor, end);
+    }
+
+    /// Delete the word before the cursor, like readline's Ctrl-W
+    pub fn delete_word_backward(&mut self) {
+        let start = self.previous_word_start(self.cursor);
+        self.delete_range(start, self.cursor);
+    }
+
+    /// Delete from the start of the line to the cursor
+    pub fn kill_to_line_start(&mut self) {
+        let start = self.line_start(self.cursor);
+        self.delete_range(start, self.cursor);
+    }
+
+    /// Delete from the cursor to the end of the line
+    pub fn kill_to_line_end(&mut self) {
+        let end = self.line_end(self.cursor);
+        self.delete_range(self.cursor, end);
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.previous_boundary(self.cursor);
+        self.last_edit = None;
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary(self.cursor);
+        self.last_edit = None;
+    }
+
+    pub fn move_line_start(&mut self) {
+        self.cursor = self.line_start(self.cursor);
+        self.last_edit = None;
+    }
+
+    pub fn move_line_end(&mut self) {
+        self.cursor = self.line_end(self.cursor);
+        self.last_edit = None;
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.previous_word_start(self.cursor);
+        self.last_edit = None;
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.next_word_end(self.cursor);
+        self.last_edit = None;
+    }
+
+    /// Restore the state before the last edit
+    pub fn undo(&mut self) {
+        if let Some((text, cursor)) = self.undo_stack.pop() {
+            self.text = text;
+            self.cursor = cursor;
+        }
+        self.last_edit = None;
+    }
+
+    /// Hard wrap the text to `width` columns.
+    /// Returns the rows and the row and column of the cursor.
+    pub fn layout(&self, width: usize) -> (Vec<String>, (usize, usize)) {
+        let width = width.max(1);
+        let mut rows = vec![String::new()];
+        let mut column = 0;
+        let mut cursor = (0, 0);
+
+        for (offset, grapheme) in self.text.grapheme_indices(true) {
+            if offset == self.cursor {
+                cursor = (rows.len() - 1, column);
+            }
+
+            if grapheme == "\n" || grapheme == "\r\n" {
+                rows.push(String::new());
+                column = 0;
+                continue;
+            }
+
+            let grapheme_width = grapheme.width();
+            if column + grapheme_width > width {
+                rows.push(String::new());
+                column = 0;
+                // The cursor moves along with a grapheme that wraps
+                if offset == self.cursor {
+                    cursor = (rows.len() - 1, 0);
+                }
+            }
+            rows.last_mut().unwrap().push_str(grapheme);
+            column += grapheme_width;
+        }
+
+        if self.cursor == self.text.len() {
+            // A cursor at the end of a full row goes to the next row
+            if column >= width {
+                rows.push(String::new());
+                column = 0;
+            }
+            cursor = (rows.len() - 1, column);
+        }
+
+        (rows, cursor)
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        self.save_undo(EditKind::Other);
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    fn save_undo(&mut self, kind: EditKind) {
+        if kind == EditKind::Insert && self.last_edit == Some(EditKind::Insert) {
+            return;
+        }
+        self.undo_stack.push((self.text.clone(), self.cursor));
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+        self.last_edit = Some(kind);
+    }
+
+    fn previous_boundary(&self, offset: usize) -> usize {
+        self.text[..offset].grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, offset: usize) -> usize {
+        self.text[offset..].graphemes(true)
+            .next()
+            .map_or(offset, |g| offset + g.len())
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..].find('\n').map_or(self.text.len(), |i| offset + i)
+    }
+
+    fn previous_word_start(&self, offset: usize) -> usize {
+        // Skip whitespace, then the word itself
+        let mut start = offset;
+        let mut in_word = false;
+        for (i, grapheme) in self.text[..offset].grapheme_indices(true).rev() {
+            let is_space = grapheme.chars().all(char::is_whitespace);
+            if in_word && is_space {
+                break;
+            }
+            in_word |= !is_space;
+            start = i;
+        }
+        start
+    }
+
+    fn next_word_end(&self, offset: usize) -> usize {
+        let mut end = offset;
+        let mut in_word = false;
+        for (i, grapheme) in self.text[offset..].grapheme_indices(true) {
+            let is_space = grapheme.chars().all(char::is_whitespace);
+            if in_word && is_space {
+                break;
+            }
+            in_word |= !is_space;
+            end = offset + i + grapheme.len();
+        }
+        end
+    }
+}
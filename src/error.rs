@@ -0,0 +1,45 @@
+use std::fmt;
+use std::io;
+
+use tokio::sync::mpsc::error::SendError;
+
+use crate::network::IoEvent;
+
+/// Errors that are reported to the user instead of ending the program
+#[derive(Debug)]
+pub enum Error {
+    /// The network thread is gone, requests can't be sent
+    NetworkClosed,
+    /// A signald response lacks a field it should carry
+    MalformedResponse(&'static str),
+    /// A request to signald failed, describes what was requested
+    Request(String),
+    /// A file operation failed, with what was attempted
+    Io(String, io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NetworkClosed => write!(f, "The connection to the network thread is closed"),
+            Error::MalformedResponse(field) => write!(f, "signald sent a response without {}", field),
+            Error::Request(what) => write!(f, "Could not {}", what),
+            Error::Io(what, e) => write!(f, "Could not {}: {}", what, e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SendError<IoEvent>> for Error {
+    fn from(_: SendError<IoEvent>) -> Self {
+        Error::NetworkClosed
+    }
+}
@@ -23,7 +23,8 @@
  */
 
 use crossterm::event;
-use std::{sync::mpsc, thread, time::Duration};
+use std::{thread, time::Duration};
+use tokio::sync::mpsc;
 use super::key::Key;
 
 #[derive(Debug, Clone, Copy)]
@@ -48,6 +49,10 @@ impl Default for EventConfig {
 pub enum Event<I> {
   /// An input event occurred.
   Input(I),
+  /// A mouse button was pressed or the wheel was scrolled.
+  Mouse(event::MouseEvent),
+  /// The terminal was resized to the given columns and rows.
+  Resize(u16, u16),
   /// An tick event occurred.
   Tick,
 }
@@ -55,9 +60,9 @@ pub enum Event<I> {
 /// A small event handler that wrap crossterm input and tick event. Each event
 /// type is handled in its own thread and returned to a common `Receiver`
 pub struct Events {
-  rx: mpsc::Receiver<Event<Key>>,
+  rx: mpsc::UnboundedReceiver<Event<Key>>,
   // Need to be kept around to prevent disposing the sender side.
-  _tx: mpsc::Sender<Event<Key>>,
+  _tx: mpsc::UnboundedSender<Event<Key>>,
 }
 
 impl Events {
@@ -71,31 +76,39 @@ impl Events {
 
   /// Constructs an new instance of `Events` from given config.
   pub fn with_config(config: EventConfig) -> Events {
-    let (tx, rx) = mpsc::channel();
+    let (tx, rx) = mpsc::unbounded_channel();
 
     let event_tx = tx.clone();
     thread::spawn(move || {
       loop {
         // poll for tick rate duration, if no event, sent tick event.
-        if event::poll(config.tick_rate).unwrap() {
-          if let event::Event::Key(key) = event::read().unwrap() {
-            let key = Key::from(key);
-
-
-            event_tx.send(Event::Input(key)).unwrap();
+        // A failed read is skipped, the next poll tries again
+        if let Ok(true) = event::poll(config.tick_rate) {
+          let event = match event::read() {
+            Ok(event::Event::Key(key)) => Some(Event::Input(Key::from(key))),
+            Ok(event::Event::Mouse(mouse)) => Some(Event::Mouse(mouse)),
+            Ok(event::Event::Resize(columns, rows)) => Some(Event::Resize(columns, rows)),
+            Err(_) => None,
+          };
+          if let Some(event) = event {
+            if event_tx.send(event).is_err() {
+              return;
+            }
           }
         }
 
-        event_tx.send(Event::Tick).unwrap();
+        // The receiver is gone once the program quits
+        if event_tx.send(Event::Tick).is_err() {
+          return;
+        }
       }
     });
 
     Events { rx, _tx: tx }
   }
 
-  /// Attempts to read an event.
-  /// This function will block the current thread.
-  pub fn next(&self) -> Result<Event<Key>, mpsc::RecvError> {
-    self.rx.recv()
+  /// Waits for the next event.
+  pub async fn next(&mut self) -> Option<Event<Key>> {
+    self.rx.recv().await
   }
 }
\ No newline at end of file
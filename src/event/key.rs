@@ -23,6 +23,7 @@
  */
 
 use crossterm::event;
+use std::str::FromStr;
 
 /// Represents an key.
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
@@ -173,6 +174,11 @@ impl From<event::KeyEvent> for Key {
         code: event::KeyCode::F(n),
         ..
       } => Key::from_f(n),
+      // Alt-Enter is used to insert newlines
+      event::KeyEvent {
+        code: event::KeyCode::Enter,
+        modifiers: event::KeyModifiers::ALT,
+      } => Key::Alt('\n'),
       event::KeyEvent {
         code: event::KeyCode::Enter,
         ..
@@ -200,4 +206,66 @@ impl From<event::KeyEvent> for Key {
       _ => Key::Unkown,
     }
   }
-}
\ No newline at end of file
+}
+
+impl FromStr for Key {
+  type Err = String;
+
+  /// Parses a key as written in the config file, e.g. `j`, `Enter`, `PageUp`, `F5`, `Ctrl-w`
+  /// or `Alt-b`. Named keys are case insensitive.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+      return Ok(Key::Char(c));
+    }
+
+    let lower = s.to_lowercase();
+    if let Some(c) = single_char(&lower, "ctrl-") {
+      return Ok(Key::Ctrl(c));
+    }
+    if lower == "alt-enter" {
+      return Ok(Key::Alt('\n'));
+    }
+    if let Some(c) = single_char(&lower, "alt-") {
+      return Ok(Key::Alt(c));
+    }
+    if lower.starts_with('f') {
+      if let Ok(n) = lower[1..].parse::<u8>() {
+        if n <= 12 {
+          return Ok(Key::from_f(n));
+        }
+      }
+    }
+
+    match lower.as_str() {
+      "enter" | "return" => Ok(Key::Enter),
+      "tab" => Ok(Key::Tab),
+      "backspace" => Ok(Key::Backspace),
+      "esc" | "escape" => Ok(Key::Esc),
+      "left" => Ok(Key::Left),
+      "right" => Ok(Key::Right),
+      "up" => Ok(Key::Up),
+      "down" => Ok(Key::Down),
+      "ins" | "insert" => Ok(Key::Ins),
+      "delete" | "del" => Ok(Key::Delete),
+      "home" => Ok(Key::Home),
+      "end" => Ok(Key::End),
+      "pageup" => Ok(Key::PageUp),
+      "pagedown" => Ok(Key::PageDown),
+      "space" => Ok(Key::Char(' ')),
+      _ => Err(format!("unknown key: {}", s)),
+    }
+  }
+}
+
+/// Returns the character following `prefix` if it is a single character
+fn single_char(s: &str, prefix: &str) -> Option<char> {
+  if !s.starts_with(prefix) {
+    return None;
+  }
+  let mut chars = s[prefix.len()..].chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) => Some(c),
+    _ => None,
+  }
+}
@@ -0,0 +1,25 @@
+use crate::app::App;
+use crate::{handlers::Handler, keymap::Action, network::IoEvent};
+
+pub struct AccountHandler {}
+
+impl Handler for AccountHandler {
+    fn handle(action: Action, app: &mut App) {
+        match action {
+            Action::NextAccount => {
+                app.select_account(app.selected_account_index + 1);
+            }
+            Action::PreviousAccount => {
+                if app.selected_account_index > 0 {
+                    app.select_account(app.selected_account_index - 1);
+                }
+            }
+            Action::SelectAccount => {
+                if let Some(account) = app.get_selected_account() {
+                    app.send_io(IoEvent::SelectAccount(account));
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,23 +1,34 @@
-use crate::app::App;
-use crate::{event::key::Key, handlers::Handler, network::{SendMessageData, IoEvent}};
+use crate::app::{App, View};
+use crate::{handlers::Handler, keymap::Action};
 
 pub struct ContactHandler {
     data: String,
 }
 
 impl Handler for ContactHandler {
-    fn handle(key: Key, app: &mut App) {
+    fn handle(action: Action, app: &mut App) {
         if !app.loaded {
             return;
         }
-        match key {
-            Key::Char('j') => {
-                // let index = app.
-                // let curindex = app.contacts.
-                app.select_conversation(app.selected_contact_index + 1);
+        match action {
+            Action::NextContact => {
+                app.select_conversation(app.selected_conversation_index + 1);
             }
-            Key::Char('k') => {
-                app.select_conversation(app.selected_contact_index - 1);
+            Action::PreviousContact => {
+                if app.selected_conversation_index > 0 {
+                    app.select_conversation(app.selected_conversation_index - 1);
+                }
+            }
+            Action::FocusInput => {
+                app.focused_view = View::Chat;
+            }
+            Action::ToggleMute => {
+                if let Some(id) = app.get_selected_conversation_id() {
+                    app.toggle_mute(&id);
+                }
+            }
+            Action::CycleFocus => {
+                app.cycle_focus();
             }
             _ => {}
         }
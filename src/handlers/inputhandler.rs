@@ -1,56 +1,133 @@
-use crate::app::App;
-use crate::{event::key::Key, handlers::Handler, network::{SendMessageData, IoEvent}};
+use crate::app::{App, View};
+use crate::completion::attach_path;
+use crate::{handlers::Handler, keymap::Action, network::{SendAttachmentData, SendMessageData, IoEvent}};
 
-pub struct InputHandler {
-    data: String,
-}
+pub struct InputHandler {}
 
 impl Handler for InputHandler {
-    fn handle(key: Key, app: &mut App) {
-        match key {
-            Key::Left => {
-                if app.input_position > 1 {
-                    app.input_position -= 1;
-                }
+    fn handle(action: Action, app: &mut App) {
+        // The attachment picker stays open while completing
+        if action != Action::Complete {
+            app.clear_completions();
+        }
+        let text_before = app.input.text().to_string();
+
+        match action {
+            Action::CursorLeft => {
+                app.input.move_left();
             }
-            Key::Right => {
-                app.input_position += 1;
-                if app.input_position > app.input_string.chars().count() {
-                    app.input_position = app.input_string.chars().count();
-                }
+            Action::CursorRight => {
+                app.input.move_right();
+            }
+            Action::LineStart => {
+                app.input.move_line_start();
+            }
+            Action::LineEnd => {
+                app.input.move_line_end();
             }
-            Key::Down => {
-                // app.items.next();
+            Action::WordLeft => {
+                app.input.move_word_left();
             }
-            Key::Up => {
-                // app.items.previous();
+            Action::WordRight => {
+                app.input.move_word_right();
             }
-            Key::Backspace => {
-                if app.input_position > 0 {
-                    let _last_c = app.input_string.remove(app.input_position - 1);
-                    app.input_position -= 1;
+            Action::FocusContacts => {
+                // Cancel a reply before leaving the input
+                if app.reply_to.take().is_none() {
+                    app.focused_view = View::Contacts;
                 }
             }
-            Key::Delete => {
-                if app.input_position < app.input_string.chars().count() {
-                    let _last_c = app.input_string.remove(app.input_position);
+            Action::CycleFocus => {
+                app.cycle_focus();
+            }
+            Action::ScrollUp => {
+                app.page_up();
+            }
+            Action::ScrollDown => {
+                app.page_down();
+            }
+            Action::ScrollTop => {
+                app.scroll_to_top();
+            }
+            Action::ScrollBottom => {
+                app.scroll_to_bottom();
+            }
+            Action::DeleteBackward => {
+                app.input.delete_backward();
+            }
+            Action::DeleteForward => {
+                app.input.delete_forward();
+            }
+            Action::DeleteWord => {
+                app.input.delete_word_backward();
+            }
+            Action::KillToLineStart => {
+                app.input.kill_to_line_start();
+            }
+            Action::KillToLineEnd => {
+                app.input.kill_to_line_end();
+            }
+            Action::Undo => {
+                app.input.undo();
+            }
+            Action::HistoryPrevious => {
+                app.recall_previous_message();
+            }
+            Action::HistoryNext => {
+                app.recall_next_message();
+            }
+            Action::InsertNewline => {
+                app.input.insert_newline();
+            }
+            Action::Complete => {
+                // Tab only completes commands, otherwise it keeps switching panes
+                if !app.complete_input() {
+                    app.cycle_focus();
                 }
             }
-            Key::Enter => {
-                if let Some(rec) = app.get_selected_contact().clone() {
-                    app.io_tx.send(IoEvent::SendMessage(SendMessageData {
-                        recipient: rec.number,
-                        message: app.input_string.clone(),
-                    })).unwrap();
-                    app.input_string.clear();
-                    app.input_position = 0;
+            Action::OpenAttachment => {
+                app.open_selected_attachment();
+            }
+            Action::SaveAttachment => {
+                app.save_selected_attachment();
+            }
+            Action::SelectMessages => {
+                app.select_messages();
+            }
+            Action::Send => {
+                if app.input.text().trim().is_empty() {
+                    return;
+                }
+                if let Some(id) = app.get_selected_conversation_id() {
+                    let is_group = app.is_group(&id);
+                    let event = match attach_path(app.input.text()) {
+                        Some(path) => IoEvent::SendAttachment(SendAttachmentData {
+                            recipient: id,
+                            is_group,
+                            path,
+                            quote: app.reply_to.take(),
+                        }),
+                        None => IoEvent::SendMessage(SendMessageData {
+                            recipient: id,
+                            is_group,
+                            message: app.input.text().to_string(),
+                            quote: app.reply_to.take(),
+                        }),
+                    };
+                    app.input.take();
+                    app.send_io(event);
+                    app.input_history_index = None;
+                    app.message_sent();
                 }
             }
-            Key::Char(x) => {
-                app.input_string.insert(app.input_position, x);
-                app.input_position += 1;
+            Action::Insert(x) => {
+                app.input.insert(x);
             }
             _ => {}
         }
+
+        if app.input.text() != text_before {
+            app.input_edited();
+        }
     }
 }
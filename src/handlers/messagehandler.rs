@@ -0,0 +1,92 @@
+use crate::app::{App, Reaction, View};
+use crate::{handlers::Handler, keymap::Action, network::{IoEvent, SendReactionData}};
+
+pub struct MessageHandler {}
+
+impl Handler for MessageHandler {
+    fn handle(action: Action, app: &mut App) {
+        match action {
+            Action::NextMessage => {
+                app.move_message_selection(1);
+            }
+            Action::PreviousMessage => {
+                app.move_message_selection(-1);
+            }
+            Action::React => {
+                match app.reaction_picker {
+                    None if !app.config.reactions.is_empty() => app.reaction_picker = Some(0),
+                    None => {}
+                    Some(index) => {
+                        send_reaction(app, index);
+                        app.reaction_picker = None;
+                    }
+                }
+            }
+            Action::Reply => {
+                app.reply_to_selected();
+            }
+            Action::NextEmoji => {
+                if let Some(index) = app.reaction_picker {
+                    app.reaction_picker = Some((index + 1) % app.config.reactions.len());
+                }
+            }
+            Action::PreviousEmoji => {
+                if let Some(index) = app.reaction_picker {
+                    let count = app.config.reactions.len();
+                    app.reaction_picker = Some((index + count - 1) % count);
+                }
+            }
+            Action::OpenAttachment => {
+                app.open_selected_attachment();
+            }
+            Action::SaveAttachment => {
+                app.save_selected_attachment();
+            }
+            Action::FocusInput => {
+                // Closing the picker keeps the selection
+                if app.reaction_picker.take().is_none() {
+                    app.selected_message = None;
+                    app.scroll_to_bottom();
+                    app.focused_view = View::Chat;
+                }
+            }
+            Action::CycleFocus => {
+                app.cycle_focus();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// React to the selected message with an emoji of the picker.
+/// Picking the emoji already reacted with removes the reaction.
+fn send_reaction(app: &mut App, index: usize) {
+    let emoji = match app.config.reactions.get(index) {
+        Some(e) => e.clone(),
+        None => return,
+    };
+    let (id, message) = match (app.get_selected_conversation_id(), app.get_selected_message()) {
+        (Some(id), Some(message)) => (id, message),
+        _ => return,
+    };
+
+    let username = app.username.clone();
+    let remove = app.conversations.get(&id)
+        .and_then(|c| c.reaction_of(&message, &username))
+        .map_or(false, |e| e == emoji);
+    let reaction = Reaction {
+        sender: username,
+        emoji,
+        target_author: message.sender,
+        target_timestamp: message.timestamp,
+        remove,
+    };
+
+    let effect = app.add_reaction(id.clone(), reaction.clone());
+    app.perform(vec![effect]);
+    app.send_io(IoEvent::SendReaction(SendReactionData {
+        is_group: app.is_group(&id),
+        recipient: id,
+        reaction,
+    }));
+}
@@ -1,9 +1,11 @@
+use crate::{app::App, keymap::Action};
 
-use crate::{event::key::Key, app::App};
-
+pub mod accounthandler;
 pub mod contacthandler;
 pub mod inputhandler;
+pub mod messagehandler;
+pub mod mousehandler;
 
 pub trait Handler {
-    fn handle(key: Key, app: &mut App);
-}
\ No newline at end of file
+    fn handle(action: Action, app: &mut App);
+}
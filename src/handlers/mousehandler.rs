@@ -0,0 +1,51 @@
+use crossterm::event::{MouseButton, MouseEvent};
+use tui::layout::Rect;
+
+use crate::app::{App, View};
+
+/// Messages scrolled per step of the mouse wheel
+const WHEEL_SCROLL: usize = 3;
+
+/// Handles mouse events using the pane areas of the last frame
+pub struct MouseHandler;
+
+impl MouseHandler {
+    pub fn handle(event: MouseEvent, app: &mut App) {
+        if !app.loaded || app.focused_view == View::Accounts {
+            return;
+        }
+        match event {
+            MouseEvent::Down(MouseButton::Left, x, y, _) => {
+                if let Some(sidebar) = app.sidebar_area.filter(|a| contains(*a, x, y)) {
+                    // Conversations start below the top border, from the first one scrolled into view
+                    if y > sidebar.y && y + 1 < sidebar.y + sidebar.height {
+                        app.select_conversation(app.sidebar_scroll + (y - sidebar.y - 1) as usize);
+                    }
+                    app.focus(View::Contacts);
+                } else if contains(app.chat_area, x, y) || contains(app.input_area, x, y) {
+                    app.focus(View::Chat);
+                }
+            }
+            MouseEvent::ScrollUp(x, y, _) => {
+                if contains(app.chat_area, x, y) {
+                    app.scroll_up(WHEEL_SCROLL);
+                } else if app.sidebar_area.map_or(false, |a| contains(a, x, y))
+                    && app.selected_conversation_index > 0 {
+                    app.select_conversation(app.selected_conversation_index - 1);
+                }
+            }
+            MouseEvent::ScrollDown(x, y, _) => {
+                if contains(app.chat_area, x, y) {
+                    app.scroll_down(WHEEL_SCROLL);
+                } else if app.sidebar_area.map_or(false, |a| contains(a, x, y)) {
+                    app.select_conversation(app.selected_conversation_index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::app::View;
+use crate::event::key::Key;
+
+/// Something the user can do, keys are mapped to actions by the `Keymap`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    CycleFocus,
+    ToggleSidebar,
+    ToggleErrorLog,
+
+    // Account picker
+    NextAccount,
+    PreviousAccount,
+    SelectAccount,
+
+    // Contact list
+    NextContact,
+    PreviousContact,
+    FocusInput,
+    ToggleMute,
+
+    // Chat
+    FocusContacts,
+    Send,
+    ScrollUp,
+    ScrollDown,
+    ScrollTop,
+    ScrollBottom,
+    CursorLeft,
+    CursorRight,
+    LineStart,
+    LineEnd,
+    WordLeft,
+    WordRight,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWord,
+    KillToLineStart,
+    KillToLineEnd,
+    InsertNewline,
+    Undo,
+    HistoryPrevious,
+    HistoryNext,
+    Complete,
+    OpenAttachment,
+    SaveAttachment,
+    SelectMessages,
+
+    // Message selection
+    NextMessage,
+    PreviousMessage,
+    React,
+    Reply,
+    NextEmoji,
+    PreviousEmoji,
+    /// Typing a character in the input, not bindable
+    Insert(char),
+}
+
+impl Action {
+    /// All bindable actions with their name in the config file
+    const NAMES: &'static [(&'static str, Action)] = &[
+        ("quit", Action::Quit),
+        ("cycle-focus", Action::CycleFocus),
+        ("toggle-sidebar", Action::ToggleSidebar),
+        ("toggle-error-log", Action::ToggleErrorLog),
+        ("next-account", Action::NextAccount),
+        ("previous-account", Action::PreviousAccount),
+        ("select-account", Action::SelectAccount),
+        ("next-contact", Action::NextContact),
+        ("previous-contact", Action::PreviousContact),
+        ("focus-input", Action::FocusInput),
+        ("toggle-mute", Action::ToggleMute),
+        ("focus-contacts", Action::FocusContacts),
+        ("send", Action::Send),
+        ("scroll-up", Action::ScrollUp),
+        ("scroll-down", Action::ScrollDown),
+        ("scroll-top", Action::ScrollTop),
+        ("scroll-bottom", Action::ScrollBottom),
+        ("cursor-left", Action::CursorLeft),
+        ("cursor-right", Action::CursorRight),
+        ("line-start", Action::LineStart),
+        ("line-end", Action::LineEnd),
+        ("word-left", Action::WordLeft),
+        ("word-right", Action::WordRight),
+        ("delete-backward", Action::DeleteBackward),
+        ("delete-forward", Action::DeleteForward),
+        ("delete-word", Action::DeleteWord),
+        ("kill-to-line-start", Action::KillToLineStart),
+        ("kill-to-line-end", Action::KillToLineEnd),
+        ("insert-newline", Action::InsertNewline),
+        ("undo", Action::Undo),
+        ("history-previous", Action::HistoryPrevious),
+        ("history-next", Action::HistoryNext),
+        ("complete", Action::Complete),
+        ("open-attachment", Action::OpenAttachment),
+        ("save-attachment", Action::SaveAttachment),
+        ("select-messages", Action::SelectMessages),
+        ("next-message", Action::NextMessage),
+        ("previous-message", Action::PreviousMessage),
+        ("react", Action::React),
+        ("reply", Action::Reply),
+        ("next-emoji", Action::NextEmoji),
+        ("previous-emoji", Action::PreviousEmoji),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::NAMES.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, a)| *a)
+    }
+}
+
+/// One or several keys bound to an action in the config file
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            KeyBinding::One(k) => vec![k.as_str()],
+            KeyBinding::Many(k) => k.iter().map(|k| k.as_str()).collect(),
+        }
+    }
+}
+
+/// Key bindings per section of the config file, e.g. `[keys.contacts]`
+pub type KeymapConfig = HashMap<String, HashMap<String, KeyBinding>>;
+
+/// Maps keys to actions.
+///
+/// Bindings are looked up in the focused view first and fall back to the global bindings.
+/// In the chat input, characters without a binding are typed.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    /// `None` holds the global bindings
+    bindings: HashMap<(Option<View>, Key), Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap { bindings: HashMap::new() };
+
+        keymap.bind(None, Key::Ctrl('c'), Action::Quit);
+        keymap.bind(None, Key::Tab, Action::CycleFocus);
+        keymap.bind(None, Key::F2, Action::ToggleSidebar);
+        keymap.bind(None, Key::F3, Action::ToggleErrorLog);
+
+        let accounts = Some(View::Accounts);
+        keymap.bind(accounts, Key::Char('q'), Action::Quit);
+        keymap.bind(accounts, Key::Char('j'), Action::NextAccount);
+        keymap.bind(accounts, Key::Down, Action::NextAccount);
+        keymap.bind(accounts, Key::Char('k'), Action::PreviousAccount);
+        keymap.bind(accounts, Key::Up, Action::PreviousAccount);
+        keymap.bind(accounts, Key::Enter, Action::SelectAccount);
+
+        let contacts = Some(View::Contacts);
+        keymap.bind(contacts, Key::Char('q'), Action::Quit);
+        keymap.bind(contacts, Key::Char('j'), Action::NextContact);
+        keymap.bind(contacts, Key::Down, Action::NextContact);
+        keymap.bind(contacts, Key::Char('k'), Action::PreviousContact);
+        keymap.bind(contacts, Key::Up, Action::PreviousContact);
+        keymap.bind(contacts, Key::Enter, Action::FocusInput);
+        keymap.bind(contacts, Key::Char('i'), Action::FocusInput);
+        keymap.bind(contacts, Key::Char('m'), Action::ToggleMute);
+
+        let chat = Some(View::Chat);
+        keymap.bind(chat, Key::Esc, Action::FocusContacts);
+        keymap.bind(chat, Key::Enter, Action::Send);
+        keymap.bind(chat, Key::PageUp, Action::ScrollUp);
+        keymap.bind(chat, Key::PageDown, Action::ScrollDown);
+        keymap.bind(chat, Key::Home, Action::ScrollTop);
+        keymap.bind(chat, Key::End, Action::ScrollBottom);
+        keymap.bind(chat, Key::Left, Action::CursorLeft);
+        keymap.bind(chat, Key::Right, Action::CursorRight);
+        keymap.bind(chat, Key::Ctrl('a'), Action::LineStart);
+        keymap.bind(chat, Key::Ctrl('e'), Action::LineEnd);
+        keymap.bind(chat, Key::Alt('b'), Action::WordLeft);
+        keymap.bind(chat, Key::Alt('f'), Action::WordRight);
+        keymap.bind(chat, Key::Backspace, Action::DeleteBackward);
+        keymap.bind(chat, Key::Delete, Action::DeleteForward);
+        keymap.bind(chat, Key::Ctrl('w'), Action::DeleteWord);
+        keymap.bind(chat, Key::Ctrl('u'), Action::KillToLineStart);
+        keymap.bind(chat, Key::Ctrl('k'), Action::KillToLineEnd);
+        keymap.bind(chat, Key::Alt('\n'), Action::InsertNewline);
+        keymap.bind(chat, Key::Ctrl('z'), Action::Undo);
+        keymap.bind(chat, Key::Up, Action::HistoryPrevious);
+        keymap.bind(chat, Key::Down, Action::HistoryNext);
+        keymap.bind(chat, Key::Tab, Action::Complete);
+        keymap.bind(chat, Key::Ctrl('o'), Action::OpenAttachment);
+        keymap.bind(chat, Key::Ctrl('s'), Action::SaveAttachment);
+        keymap.bind(chat, Key::Alt('s'), Action::SelectMessages);
+
+        let messages = Some(View::Messages);
+        keymap.bind(messages, Key::Esc, Action::FocusInput);
+        keymap.bind(messages, Key::Char('i'), Action::FocusInput);
+        keymap.bind(messages, Key::Char('j'), Action::NextMessage);
+        keymap.bind(messages, Key::Down, Action::NextMessage);
+        keymap.bind(messages, Key::Char('k'), Action::PreviousMessage);
+        keymap.bind(messages, Key::Up, Action::PreviousMessage);
+        keymap.bind(messages, Key::Char('e'), Action::React);
+        keymap.bind(messages, Key::Enter, Action::React);
+        keymap.bind(messages, Key::Char('r'), Action::Reply);
+        keymap.bind(messages, Key::Char('l'), Action::NextEmoji);
+        keymap.bind(messages, Key::Right, Action::NextEmoji);
+        keymap.bind(messages, Key::Char('h'), Action::PreviousEmoji);
+        keymap.bind(messages, Key::Left, Action::PreviousEmoji);
+        keymap.bind(messages, Key::Ctrl('o'), Action::OpenAttachment);
+        keymap.bind(messages, Key::Ctrl('s'), Action::SaveAttachment);
+
+        let errors = Some(View::Errors);
+        keymap.bind(errors, Key::Esc, Action::ToggleErrorLog);
+        keymap.bind(errors, Key::Char('q'), Action::ToggleErrorLog);
+
+        keymap
+    }
+}
+
+impl Keymap {
+    /// The default keymap with the bindings of the config applied.
+    /// Binding an action replaces its default keys in that section.
+    pub fn from_config(config: &KeymapConfig) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+
+        for (section, actions) in config.iter() {
+            let view = match section.as_str() {
+                "global" => None,
+                "accounts" => Some(View::Accounts),
+                "contacts" => Some(View::Contacts),
+                "chat" => Some(View::Chat),
+                "messages" => Some(View::Messages),
+                "errors" => Some(View::Errors),
+                _ => return Err(format!("unknown key section: {}", section)),
+            };
+
+            for (name, binding) in actions.iter() {
+                let action = Action::from_name(name)
+                    .ok_or_else(|| format!("unknown action: {}", name))?;
+
+                keymap.bindings.retain(|(v, _), a| !(*v == view && *a == action));
+                for key in binding.keys() {
+                    keymap.bind(view, key.parse()?, action);
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn bind(&mut self, view: Option<View>, key: Key, action: Action) {
+        self.bindings.insert((view, key), action);
+    }
+
+    /// The action for a key pressed in a view
+    pub fn action(&self, view: View, key: Key) -> Option<Action> {
+        if let Some(action) = self.bindings.get(&(Some(view), key)) {
+            return Some(*action);
+        }
+        if let (View::Chat, Key::Char(c)) = (view, key) {
+            return Some(Action::Insert(c));
+        }
+        self.bindings.get(&(None, key)).cloned()
+    }
+}
@@ -0,0 +1,16 @@
+pub mod attachment;
+pub mod common;
+pub mod completion;
+pub mod config;
+pub mod editor;
+pub mod error;
+pub mod network;
+pub mod notify;
+pub mod signald;
+pub mod event;
+pub mod app;
+pub mod handlers;
+pub mod keymap;
+pub mod store;
+pub mod ui;
+pub mod update;
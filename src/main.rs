@@ -1,76 +1,123 @@
 use std::error::Error;
 use std::io::{Write, stdout};
-use std::sync::Arc;
-use std::{time::Duration, sync::mpsc::{Sender, Receiver}};
+use std::{panic, process};
 
-use crossterm::cursor::MoveTo;
+use crossterm::cursor::{MoveTo, Show};
 use crossterm::ExecutableCommand;
-use tokio::sync::Mutex;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tui::backend::CrosstermBackend;
+use tui::layout::Rect;
 use tui::Terminal;
 
-use crate::app::{App, View};
-use crate::event::event::{Event, Events};
-use crate::handlers::Handler;
-use crate::handlers::inputhandler::InputHandler;
-use crate::network::{IoEvent, Network};
-use crate::ui::draw_basic_view;
-use event::key::Key;
+use signald_tui::app::{App, View};
+use signald_tui::config::Config;
+use signald_tui::event::event::{Event, Events};
+use signald_tui::handlers::Handler;
+use signald_tui::keymap::Action;
+use signald_tui::handlers::accounthandler::AccountHandler;
+use signald_tui::handlers::inputhandler::InputHandler;
+use signald_tui::handlers::messagehandler::MessageHandler;
+use signald_tui::handlers::mousehandler::MouseHandler;
+use signald_tui::network::{IoEvent, Network};
+use signald_tui::notify::{self, Bell};
+use signald_tui::ui::draw_basic_view;
+use signald_tui::update::StateUpdate;
+use signald_tui::ui::image::{self, ImagePlacement, ImageProtocol};
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use crate::handlers::contacthandler::ContactHandler;
-
-pub mod common;
-pub mod network;
-pub mod event;
-pub mod app;
-pub mod handlers;
-pub mod ui;
+use signald_tui::handlers::contacthandler::ContactHandler;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
 
+    let config = Config::load()?;
+
+    // A panic in any thread would leave the terminal in raw mode and hide the message
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+        process::exit(1);
+    }));
+
+    let result = run(config).await;
+    restore_terminal();
+    result
+}
+
+async fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let socket_path = config.socket_path.clone();
+    let preferred_account = config.account.clone();
+    let bell = Bell::default();
+    let notifier = notify::from_config(&config.notifications, bell.clone());
+
     // Terminal setup
     enable_raw_mode()?;
     let mut stdout = stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // Io setup
-    let (tx, rx) = std::sync::mpsc::channel::<IoEvent>();
-    let txclone = tx.clone();
-    let app = Arc::new(Mutex::new(App::new(tx)));
+    let (tx, rx) = mpsc::unbounded_channel::<IoEvent>();
+    let (updates_tx, mut updates_rx) = mpsc::unbounded_channel::<StateUpdate>();
+    let mut app = App::with_config(tx, config);
+    app.set_notifier(notifier);
 
     // Network setup
-    let appclone = Arc::clone(&app);
     std::thread::spawn(move || {
-    let network = Network::new(appclone);
-        handle_network_io(txclone, rx, network);
+        let mut network = Network::with_socket_path(updates_tx, &socket_path);
+        if let Some(account) = preferred_account {
+            network.set_preferred_account(account);
+        }
+        handle_network_io(network, rx);
     });
 
     // Initial network setup
-    {
-        let mut mutapp = app.lock().await;
-        mutapp.io_tx.send(IoEvent::LoadAccount)?;
-        mutapp.io_tx.send(IoEvent::Subscribe)?;
-        mutapp.io_tx.send(IoEvent::GetContactList)?;
-    }
+    app.send_io(IoEvent::LoadAccount);
 
     terminal.clear()?;
 
-    let events: Events = Events::new(250);
+    let mut events: Events = Events::new(1000);
+    let mut placements: Vec<ImagePlacement> = Vec::new();
     loop {
 
-        let mut app = app.lock().await;
-
         // Render the UI
         terminal.draw(|mut f| {
             draw_basic_view(&mut f, &mut app);
         })?;
 
+        // Images drawn with a graphics protocol aren't part of the frame, when they move
+        // the screen is redrawn to remove them and they are drawn again
+        if app.image_placements != placements {
+            if !placements.is_empty() {
+                if app.image_protocol == ImageProtocol::Kitty {
+                    image::clear_kitty_images(terminal.backend_mut())?;
+                }
+                terminal.clear()?;
+                terminal.draw(|mut f| {
+                    draw_basic_view(&mut f, &mut app);
+                })?;
+            }
+            for placement in app.image_placements.iter() {
+                let preview = app.image_previews.get(&placement.path).and_then(|p| p.as_ref());
+                if let Some(sequence) = preview.and_then(|p| p.encode(app.image_protocol)) {
+                    terminal.backend_mut().execute(MoveTo(placement.x, placement.y))?;
+                    terminal.backend_mut().write_all(sequence.as_bytes())?;
+                }
+            }
+            terminal.backend_mut().flush()?;
+            placements = app.image_placements.clone();
+        }
+
+        if bell.take() {
+            terminal.backend_mut().write_all(b"\x07")?;
+            terminal.backend_mut().flush()?;
+        }
+
         // Update cursor information
         terminal.backend_mut().execute(MoveTo(
            app.cursor_pos.x as u16, app.cursor_pos.y as u16,
@@ -81,44 +128,92 @@ async fn main() -> Result<(), Box<dyn Error>> {
             terminal.hide_cursor()?;
         }
 
+        // Wait for input, or for the network to change the app
+        let event = tokio::select! {
+            event = events.next() => event,
+            Some(update) = updates_rx.recv() => {
+                apply(&mut app, update);
+                None
+            }
+        };
+        // Several changes in a row only need one redraw
+        while let Ok(update) = updates_rx.try_recv() {
+            apply(&mut app, update);
+        }
+
+        let event = match event {
+            Some(e) => e,
+            None => continue,
+        };
+
         // Handle user input
-        match events.next()? {
-            Event::Input(input) => match input {
-                Key::Char('q') => {
-                    disable_raw_mode()?;
-                    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                    terminal.show_cursor()?;
+        match event {
+            Event::Input(input) => match app.keymap.action(app.focused_view, input) {
+                Some(Action::Quit) => {
+                    app.save_drafts();
+                    app.stop_typing();
                     break;
                 }
-                _ => {
+                Some(Action::ToggleSidebar) => {
+                    app.toggle_sidebar();
+                }
+                Some(Action::ToggleErrorLog) => {
+                    app.toggle_error_log();
+                }
+                Some(action) => {
                     match app.focused_view {
+                        View::Accounts => {
+                            AccountHandler::handle(action, &mut app);
+                        }
                         View::Contacts => {
-                            ContactHandler::handle(input, &mut app);
+                            ContactHandler::handle(action, &mut app);
                         }
                         View::Chat => {
-                            InputHandler::handle(input, &mut app);
+                            InputHandler::handle(action, &mut app);
                         }
+                        View::Messages => {
+                            MessageHandler::handle(action, &mut app);
+                        }
+                        View::Errors => {}
                     }
                 }
+                None => {}
+            },
+            Event::Mouse(mouse) => {
+                MouseHandler::handle(mouse, &mut app);
+            }
+            Event::Resize(columns, rows) => {
+                // Images drawn with a graphics protocol don't move with the layout
+                if app.image_protocol == ImageProtocol::Kitty {
+                    image::clear_kitty_images(terminal.backend_mut())?;
+                }
+                terminal.resize(Rect::new(0, 0, columns, rows))?;
+                placements.clear();
+            }
+            Event::Tick => {
+                app.check_typing();
+                app.expire_notice();
             },
-            Event::Tick => {},
         }
     }
 
     Ok(())
 }
 
+/// Leave the alternate screen and raw mode, safe to call more than once
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture, Show);
+}
+
+/// Apply a state update sent by the network and perform its effects
+fn apply(app: &mut App, update: StateUpdate) {
+    let effects = app.apply(update);
+    app.perform(effects);
+}
+
+/// Run the network on its own runtime until the app drops its event sender
 #[tokio::main]
-pub async fn handle_network_io(tx: Sender<IoEvent>, rx: Receiver<IoEvent>, mut network: Network) {
-    std::thread::spawn(move || {
-        let tx = tx.clone();
-        let duration = Duration::from_micros(250);
-        loop {
-            tx.send(IoEvent::Tick).unwrap();
-            std::thread::sleep(duration);
-        }
-    });
-    while let Ok(event) = rx.recv() {
-        network.handle_event(event).await;
-    }
-}
\ No newline at end of file
+pub async fn handle_network_io(network: Network, rx: UnboundedReceiver<IoEvent>) {
+    network.run(rx).await;
+}
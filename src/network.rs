@@ -1,133 +1,398 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
 
-use futures::SinkExt;
-use signald_rust::signald::Signald;
-use signald_rust::signaldresponse::{Account, ResponseType, SignaldResponse};
-use tokio::sync::Mutex;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::time::delay_until;
 
-use crate::app::{App, Message, Contact};
-use bus::BusReader;
+use crate::app::{ConnectionState, Message, Contact, DeliveryStatus, Group, Quote, Reaction};
+use crate::attachment::Attachment;
+use crate::error::Error;
+use crate::signald::client::Signald;
+use crate::signald::request::SignaldRequestBuilder;
+use crate::signald::response::{ResponseType, SignaldResponse};
+use crate::signald::response::Attachment as SignaldAttachment;
+use crate::signald::response::Quote as SignaldQuote;
+use crate::signald::response::Reaction as SignaldReaction;
+use crate::ui::image::Preview;
+use crate::update::StateUpdate;
+use std::path::PathBuf;
 use std::time::{UNIX_EPOCH, SystemTime};
 
+/// Delay before the first attempt to reconnect, doubled after every failed attempt
+const RECONNECT_MIN_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
 pub enum IoEvent {
     Subscribe,
     GetContactList,
+    GetGroupList,
     SendMessage(SendMessageData),
+    SendAttachment(SendAttachmentData),
+    SendReaction(SendReactionData),
+    SendTyping(SendTypingData),
     LoadAccount,
-    Tick,
+    SelectAccount(String),
+    LoadPreview(LoadPreviewData),
 }
 
+#[derive(Debug)]
 pub struct SendMessageData {
     pub recipient: String,
+    /// Whether the recipient is a group id
+    pub is_group: bool,
     pub message: String,
+    /// The message replied to
+    pub quote: Option<Quote>,
+}
+
+#[derive(Debug)]
+pub struct SendAttachmentData {
+    pub recipient: String,
+    pub is_group: bool,
+    pub path: PathBuf,
+    /// The message replied to
+    pub quote: Option<Quote>,
+}
+
+/// A message or attachment written while disconnected
+#[derive(Debug)]
+enum Outgoing {
+    Message(SendMessageData),
+    Attachment(SendAttachmentData),
+}
+
+#[derive(Debug)]
+pub struct SendReactionData {
+    pub recipient: String,
+    pub is_group: bool,
+    pub reaction: Reaction,
+}
+
+#[derive(Debug)]
+pub struct SendTypingData {
+    pub recipient: String,
+    pub is_group: bool,
+    /// Whether typing started or stopped
+    pub started: bool,
+}
+
+/// An image attachment to decode for the chat, scaled to fit in `max_columns` by `max_rows` cells
+#[derive(Debug)]
+pub struct LoadPreviewData {
+    pub path: String,
+    pub max_columns: u16,
+    pub max_rows: u16,
 }
 
 pub struct Network {
     username: String,
-    app: Arc<Mutex<App>>,
+    /// Changes to the app state, applied by the UI
+    updates: UnboundedSender<StateUpdate>,
+    socket_path: String,
+    /// The account to load when several are registered
+    preferred_account: Option<String>,
     pub signald: Signald,
-    bus_rx: BusReader<SignaldResponse>,
+    connection: ConnectionState,
+    /// Responses and incoming messages of the current connection
+    responses: UnboundedReceiver<SignaldResponse>,
+    /// Failed attempts to reconnect since the connection was lost
+    reconnect_attempts: u32,
+    /// Messages written while disconnected with their timestamps, sent once reconnected
+    outbox: VecDeque<(Outgoing, i64)>,
 }
 
 impl Network {
-    pub fn new(app: Arc<Mutex<App>>) -> Self {
-        let mut signald = Signald::connect();
-        let bus_rx = signald.get_rx();
+    pub fn new(updates: UnboundedSender<StateUpdate>) -> Self {
+        Network::with_socket_path(updates, crate::common::SOCKET_PATH)
+    }
+
+    /// Connect to a signald socket at a specific path
+    pub fn with_socket_path(updates: UnboundedSender<StateUpdate>, socket_path: &str) -> Self {
+        let (signald, responses) = Signald::connect_path(socket_path);
         Self {
             username: "".to_string(),
-            app,
+            updates,
+            socket_path: socket_path.to_string(),
+            preferred_account: None,
             signald,
-            bus_rx,
+            connection: ConnectionState::Connecting,
+            responses,
+            reconnect_attempts: 0,
+            outbox: VecDeque::new(),
         }
     }
 
+    pub fn set_preferred_account(&mut self, account: String) {
+        self.preferred_account = Some(account);
+    }
+
     pub async fn handle_event(&mut self, io_event: IoEvent) {
         match io_event {
             IoEvent::GetContactList => {
                 self.get_contact_list().await;
             }
+            IoEvent::GetGroupList => {
+                self.get_group_list().await;
+            }
             IoEvent::Subscribe => {
                 self.subscribe().await;
             }
             IoEvent::SendMessage(d) => {
                 self.send_message(d).await;
             }
+            IoEvent::SendAttachment(d) => {
+                self.send_attachment(d).await;
+            }
+            IoEvent::SendReaction(d) => {
+                self.send_reaction(d).await;
+            }
+            IoEvent::SendTyping(d) => {
+                self.send_typing(d).await;
+            }
             IoEvent::LoadAccount => {
                 self.load_accounts().await;
             }
-            IoEvent::Tick => {
-                self.handle_responses().await;
+            IoEvent::SelectAccount(username) => {
+                self.set_account(username).await;
+            }
+            IoEvent::LoadPreview(d) => {
+                self.load_preview(d).await;
             }
         }
     }
 
-    pub async fn handle_responses(&mut self) {
-        while let Ok(res) = self.bus_rx.try_recv() {
-            match res.data {
-                ResponseType::BusUpdate => {}
-                ResponseType::Message(message) => {
-                    let message = message.unwrap();
-                    // Received sync message
-                    if message.sync_message.is_some() {
-                        let sync = message.sync_message.unwrap();
-                        if sync.sent.is_some() {
-                            let sent = sync.sent.unwrap();
-                            let mesg = sent.message.message;
+    /// Handle events and signald responses as they arrive, until the app drops its event sender
+    pub async fn run(mut self, mut io_rx: UnboundedReceiver<IoEvent>) {
+        loop {
+            let retry_at = match self.connection {
+                ConnectionState::Disconnected { retry_at } => Some(retry_at),
+                _ => None,
+            };
+            let reconnect = delay_until(retry_at.unwrap_or_else(Instant::now).into());
+
+            tokio::select! {
+                event = io_rx.recv() => match event {
+                    Some(event) => self.handle_event(event).await,
+                    None => break,
+                },
+                // The channel closes when signald closes the socket
+                response = self.responses.recv(), if retry_at.is_none() => match response {
+                    Some(response) => self.handle_response(response).await,
+                    None => self.connection_lost(),
+                },
+                _ = reconnect, if retry_at.is_some() => self.check_connection().await,
+            }
+        }
+    }
+
+    /// Handle the responses that already arrived and reconnect when it is time, without waiting
+    pub async fn poll(&mut self) {
+        loop {
+            match self.responses.try_recv() {
+                Ok(response) => self.handle_response(response).await,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Closed) => {
+                    self.connection_lost();
+                    break;
+                }
+            }
+        }
+        self.check_connection().await;
+    }
+
+    async fn handle_response(&mut self, res: SignaldResponse) {
+        match res.data {
+            ResponseType::Message(message) => {
+                let message = match message {
+                    Some(m) => *m,
+                    None => {
+                        self.update(StateUpdate::Failed(Error::MalformedResponse("the message")));
+                        return;
+                    }
+                };
+                // Received sync message
+                if let Some(sync) = message.sync_message {
+                    if let Some(sent) = sync.sent {
 
+                        // Messages sent to a group are stored in the group conversation
+                        let conversation = match &sent.message.group_info {
+                            Some(group) => group.group_id.clone(),
+                            None => sent.destination.clone(),
+                        };
+
+                        // Reactions sent from another device
+                        if let Some(reaction) = sent.message.reaction {
+                            let reaction = convert_reaction(reaction, self.username.clone());
+                            self.update(StateUpdate::ReactionReceived { conversation, reaction });
+                        } else {
                             let tui_message = Message {
-                                message: mesg,
+                                attachments: convert_attachments(&sent.message.attachments),
+                                message: sent.message.message,
                                 sender: self.username.clone(),
-                                receiver: sent.destination.clone(),
+                                receiver: conversation.clone(),
                                 timestamp: sent.timestamp,
+                                status: DeliveryStatus::Sent,
+                                quote: sent.message.quote.map(convert_quote),
                             };
+                            self.update(StateUpdate::MessageReceived { conversation, message: tui_message });
+                        }
+                    }
+                }
+                // Received delivery or read receipt
+                if let Some(receipt) = message.receipt {
+                    let status = match receipt.r#type.as_str() {
+                        "DELIVERY" => Some(DeliveryStatus::Delivered),
+                        "READ" => Some(DeliveryStatus::Read),
+                        _ => None,
+                    };
 
-                            let mut mutapp = self.app.lock().await;
-                            if let Some(conv) = mutapp.get_conversation(sent.destination) {
-                                conv.messages.push(tui_message);
-                            }
+                    if let Some(status) = status {
+                        for timestamp in receipt.timestamps {
+                            self.update(StateUpdate::MessageStatusChanged { timestamp, status });
                         }
                     }
-                    // Received data message
-                    if message.data_message.is_some() {
-                        let mesg = message.data_message.unwrap();
+                }
+                // Received typing indicator
+                if let (Some(typing), Some(source)) = (&message.typing, &message.source) {
+                    self.update(StateUpdate::TypingChanged {
+                        conversation: typing.group_id.clone().unwrap_or_else(|| source.clone()),
+                        sender: source.clone(),
+                        started: typing.action == "STARTED",
+                    });
+                }
+                // Received data message
+                if let Some(mesg) = message.data_message {
+                    let source = match message.source {
+                        Some(s) => s,
+                        None => {
+                            self.update(StateUpdate::Failed(Error::MalformedResponse("the sender of a message")));
+                            return;
+                        }
+                    };
+
+                    // Group updates without a body carry no message
+                    let is_group_update = mesg.group_info.is_some()
+                        && mesg.message.is_empty()
+                        && mesg.attachments.is_empty();
+                    let attachments = convert_attachments(&mesg.attachments);
 
-                        let source = message.source.unwrap();
+                    let (conversation, receiver) = match mesg.group_info {
+                        Some(info) => {
+                            let id = info.group_id;
+                            self.update(StateUpdate::GroupInfoReceived {
+                                id: id.clone(),
+                                name: info.name,
+                                members: info.members,
+                            });
+                            (id.clone(), id)
+                        }
+                        None => (source.clone(), self.username.clone()),
+                    };
+
+                    if let Some(reaction) = mesg.reaction {
+                        let reaction = convert_reaction(reaction, source);
+                        self.update(StateUpdate::ReactionReceived { conversation, reaction });
+                    } else if !is_group_update {
                         let tui_message = Message {
                             message: mesg.message,
-                            sender: source.clone(),
-                            receiver: self.username.clone(),
+                            sender: source,
+                            receiver,
                             timestamp: mesg.timestamp,
+                            status: DeliveryStatus::Received,
+                            quote: mesg.quote.map(convert_quote),
+                            attachments,
                         };
 
-                        let mut mutapp = self.app.lock().await;
-                        if let Some(conv) = mutapp.get_conversation(source) {
-                            conv.messages.push(tui_message);
-                        }
+                        self.update(StateUpdate::MessageReceived { conversation, message: tui_message });
                     }
                 }
-                ResponseType::Version(_) => {}
-                ResponseType::ContactList(_) => {}
-                ResponseType::LinkingUri(_) => {}
-                ResponseType::LinkingError(_) => {}
-                ResponseType::Subscribed => {}
-                ResponseType::Unsubscribed => {}
-                ResponseType::Unknown(_, _) => {}
-                _ => {}
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a change to the app state to the UI.
+    /// The UI is gone when the channel is closed, the network stops once the events end as well.
+    fn update(&self, update: StateUpdate) {
+        let _ = self.updates.send(update);
+    }
+
+    /// Track the state of the connection and show it
+    fn set_connection(&mut self, connection: ConnectionState) {
+        self.connection = connection;
+        self.update(StateUpdate::ConnectionChanged(connection));
+    }
+
+    /// Mark the connection as lost and schedule an attempt to reconnect
+    fn connection_lost(&mut self) {
+        if let ConnectionState::Disconnected { .. } = self.connection {
+            return;
+        }
+        let retry_at = Instant::now() + reconnect_delay(self.reconnect_attempts);
+        self.set_connection(ConnectionState::Disconnected { retry_at });
+    }
+
+    /// Reconnect once the backoff delay has passed, then restore the account and subscription
+    async fn check_connection(&mut self) {
+        match self.connection {
+            ConnectionState::Disconnected { retry_at } if Instant::now() >= retry_at => {}
+            _ => return,
+        }
+
+        // Probe the socket first, connecting the client to a missing socket would fail for good
+        if UnixStream::connect(&self.socket_path).is_err() {
+            self.reconnect_attempts += 1;
+            let retry_at = Instant::now() + reconnect_delay(self.reconnect_attempts);
+            self.set_connection(ConnectionState::Disconnected { retry_at });
+            return;
+        }
+
+        self.set_connection(ConnectionState::Connecting);
+        let (signald, responses) = Signald::connect_path(&self.socket_path);
+        self.signald = signald;
+        self.responses = responses;
+        self.reconnect_attempts = 0;
+
+        if self.username.is_empty() {
+            self.load_accounts().await;
+        } else {
+            self.resume_account().await;
+        }
+    }
+
+    /// Send the messages written while disconnected
+    async fn flush_outbox(&mut self) {
+        while let Some((outgoing, timestamp)) = self.outbox.pop_front() {
+            match outgoing {
+                Outgoing::Message(data) => self.deliver_message(data, timestamp).await,
+                Outgoing::Attachment(data) => self.deliver_attachment(data, timestamp).await,
             }
         }
     }
 
     async fn subscribe(&mut self) {
-        if let Ok(_) = self.signald.subscribe(self.username.clone()).await {}
+        if self.signald.subscribe(self.username.clone()).await.is_err() {
+            self.update(StateUpdate::Failed(Error::Request("subscribe to incoming messages".to_string())));
+        }
     }
 
     async fn get_contact_list(&mut self) {
-        if let Ok(res) = self.signald.list_contacts(self.username.clone()).await {
+        let res = self.signald.list_contacts(self.username.clone()).await;
+        if res.is_err() {
+            self.update(StateUpdate::Failed(Error::Request("load the contact list".to_string())));
+        }
+        if let Ok(res) = res {
             match res.data {
-                ResponseType::ContactList(a) => {
+                ResponseType::ContactList(None) => {
+                    self.update(StateUpdate::Failed(Error::MalformedResponse("the contact list")));
+                }
+                ResponseType::ContactList(Some(a)) => {
                     let mut contacts: Vec<Contact> = Vec::new();
 
-                    for account in a.unwrap().iter() {
+                    for account in a.iter() {
 
                         let contact = Contact {
                             name: account.name.clone(),
@@ -137,8 +402,33 @@ impl Network {
 
                         contacts.push(contact);
                     }
-                    let mut app = self.app.lock().await;
-                    app.update_contacts(contacts);
+                    self.update(StateUpdate::ContactsLoaded(contacts));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn get_group_list(&mut self) {
+        let res = self.signald.list_groups(self.username.clone()).await;
+        if res.is_err() {
+            self.update(StateUpdate::Failed(Error::Request("load the group list".to_string())));
+        }
+        if let Ok(res) = res {
+            match res.data {
+                ResponseType::GroupList(None) => {
+                    self.update(StateUpdate::Failed(Error::MalformedResponse("the group list")));
+                }
+                ResponseType::GroupList(Some(g)) => {
+                    let groups: Vec<Group> = g.groups.iter()
+                        .map(|group| Group {
+                            id: group.group_id.clone(),
+                            name: group.name.clone(),
+                            members: group.members.clone().unwrap_or_default(),
+                        })
+                        .collect();
+
+                    self.update(StateUpdate::GroupsLoaded(groups));
                 }
                 _ => {}
             }
@@ -146,48 +436,245 @@ impl Network {
     }
 
     async fn send_message(&mut self, data: SendMessageData) {
-        self.signald
-            .send(
-                self.username.clone(),
-                data.recipient.clone(),
-                Some(data.message.clone()),
-            )
-            .await;
-
-        let mut app = self.app.lock().await;
-        let start = SystemTime::now();
-        let datetime = start.duration_since(UNIX_EPOCH).expect("Time looped over");
+        let timestamp = now_millis();
+
+        // Show the message right away, the status is updated once signald responds
+        let mesg = Message {
+            message: data.message.clone(),
+            receiver: data.recipient.clone(),
+            sender: self.username.clone(),
+            timestamp,
+            status: DeliveryStatus::Sending,
+            attachments: Vec::new(),
+            quote: data.quote.clone(),
+        };
+        self.update(StateUpdate::MessageQueued { conversation: data.recipient.clone(), message: mesg });
+        if self.connection != ConnectionState::Connected {
+            self.outbox.push_back((Outgoing::Message(data), timestamp));
+            return;
+        }
+
+        self.deliver_message(data, timestamp).await;
+    }
+
+    /// Send a message that is already shown in the conversation
+    async fn deliver_message(&mut self, data: SendMessageData, timestamp: i64) {
+        // Without a timestamp signald stamps the message itself, while receipts, reactions and
+        // quotes refer to the message by the timestamp it is stored under
+        let mut builder = SignaldRequestBuilder::new()
+            .set_message_body(Some(data.message.clone()))
+            .set_timestamp(timestamp);
+        if let Some(quote) = data.quote {
+            builder = builder.set_quote(quote.id, quote.author, quote.text);
+        }
+        let status = self.send_custom(&data.recipient, data.is_group, "send", builder).await;
+
+        self.update(StateUpdate::MessageStatusChanged { timestamp, status });
+        if status == DeliveryStatus::Failed {
+            self.update(StateUpdate::SendFailed { recipient: data.recipient, what: "message" });
+        }
+    }
+
+    /// Upload a file to a conversation. The message shows as uploading until signald responds,
+    /// while disconnected it is queued like a message.
+    async fn send_attachment(&mut self, data: SendAttachmentData) {
+        let timestamp = now_millis();
+        let attachment = Attachment::from_path(&data.path);
+        let readable = attachment.size.is_some();
+
         let mesg = Message {
-            message: data.message,
+            message: String::new(),
             receiver: data.recipient.clone(),
-            sender: app.username.clone(),
-            timestamp: datetime.as_millis() as i64,
+            sender: self.username.clone(),
+            timestamp,
+            status: DeliveryStatus::Sending,
+            quote: data.quote.clone(),
+            attachments: vec![attachment],
         };
-        if let Some(conv) = app.get_conversation(data.recipient) {
-            conv.messages.push(mesg);
+        self.update(StateUpdate::MessageQueued { conversation: data.recipient.clone(), message: mesg });
+
+        // A missing file would otherwise only be reported by signald
+        if !readable {
+            let status = DeliveryStatus::Failed;
+            self.update(StateUpdate::MessageStatusChanged { timestamp, status });
+            self.update(StateUpdate::Failed(Error::Request(format!("read {}", data.path.display()))));
+            return;
+        }
+        if self.connection != ConnectionState::Connected {
+            self.outbox.push_back((Outgoing::Attachment(data), timestamp));
+            return;
+        }
+
+        self.deliver_attachment(data, timestamp).await;
+    }
+
+    /// Send an attachment that is already shown in the conversation
+    async fn deliver_attachment(&mut self, data: SendAttachmentData, timestamp: i64) {
+        let mut builder = SignaldRequestBuilder::new()
+            .set_attachments(vec![data.path.display().to_string()])
+            .set_timestamp(timestamp);
+        if let Some(quote) = data.quote {
+            builder = builder.set_quote(quote.id, quote.author, quote.text);
+        }
+        let status = self.send_custom(&data.recipient, data.is_group, "send", builder).await;
+        self.update(StateUpdate::MessageStatusChanged { timestamp, status });
+        if status == DeliveryStatus::Failed {
+            self.update(StateUpdate::SendFailed { recipient: data.recipient, what: "attachment" });
+        }
+    }
+
+    /// Send a reaction that is already shown in the conversation
+    async fn send_reaction(&mut self, data: SendReactionData) {
+        let reaction = data.reaction;
+        let builder = SignaldRequestBuilder::new()
+            .set_reaction(reaction.emoji, reaction.remove, reaction.target_author, reaction.target_timestamp);
+        if self.send_custom(&data.recipient, data.is_group, "send", builder).await == DeliveryStatus::Failed {
+            self.update(StateUpdate::SendFailed { recipient: data.recipient, what: "reaction" });
+        }
+    }
+
+    /// Decode an image attachment. Large photos take a while to decode,
+    /// so this runs on the blocking pool.
+    async fn load_preview(&mut self, data: LoadPreviewData) {
+        let LoadPreviewData { path, max_columns, max_rows } = data;
+        let file = path.clone();
+        let preview = tokio::task::spawn_blocking(move || Preview::load(&file, max_columns, max_rows))
+            .await
+            .unwrap_or(None);
+        self.update(StateUpdate::PreviewLoaded { path, preview });
+    }
+
+    /// Tell a conversation the user started or stopped typing.
+    /// Typing indicators are best effort, so the response isn't waited for.
+    async fn send_typing(&mut self, data: SendTypingData) {
+        let request_type = if data.started { "typing_started" } else { "typing_stopped" };
+        let request = self.address(&data.recipient, data.is_group, request_type, SignaldRequestBuilder::new()).build();
+        let _ = self.signald.send_request(&request);
+    }
+
+    /// Send a request of `request_type` built on `builder` to a conversation and wait for the response
+    async fn send_custom(&mut self, recipient: &str, is_group: bool, request_type: &str, builder: SignaldRequestBuilder) -> DeliveryStatus {
+        let builder = self.address(recipient, is_group, request_type, builder);
+        match self.signald.request(builder.build()).await {
+            Ok(_) => DeliveryStatus::Sent,
+            Err(_) => DeliveryStatus::Failed,
+        }
+    }
+
+    /// Address a request of `request_type` to a conversation
+    fn address(&self, recipient: &str, is_group: bool, request_type: &str, builder: SignaldRequestBuilder) -> SignaldRequestBuilder {
+        let builder = builder
+            .set_type(request_type.to_string())
+            .set_username(self.username.clone())
+            .set_id(Signald::get_random_id());
+        if is_group {
+            builder.set_recipient_group_id(recipient.to_string())
+        } else {
+            builder.set_recipient_number(recipient.to_string())
         }
     }
 
     async fn load_accounts(&mut self) {
 
-        if let Ok(res) = self.signald.list_accounts().await {
+        let res = self.signald.list_accounts().await;
+        if res.is_err() {
+            self.connection_lost();
+        }
+        if let Ok(res) = res {
+            // Accounts are listed in every session, an answer means the connection works
+            self.set_connection(ConnectionState::Connected);
 
             match res.data {
-                ResponseType::AccountList(a) => {
-                    let accounts = a.unwrap().accounts;
+                ResponseType::AccountList(None) => {
+                    self.update(StateUpdate::Failed(Error::MalformedResponse("the account list")));
+                }
+                ResponseType::AccountList(Some(a)) => {
+                    let accounts: Vec<String> = a.accounts.iter()
+                        .map(|a| a.username.clone())
+                        .collect();
 
-                    if accounts.len() == 0 {
-                        panic!("No signald account found! exiting");
-                    }
-                    let username = accounts.get(0).unwrap().username.clone();
-                    self.username = username.clone();
+                    let account = match &self.preferred_account {
+                        Some(p) => accounts.iter().find(|a| *a == p).cloned(),
+                        None if accounts.len() == 1 => accounts.get(0).cloned(),
+                        None => None,
+                    };
 
-                    let mut app = self.app.lock().await;
-                    app.username = username;
-                    app.loaded = true;
+                    match account {
+                        Some(username) => self.set_account(username).await,
+                        None => self.update(StateUpdate::AccountsLoaded(accounts)),
+                    }
                 }
                 _ => {}
             }
         }
     }
+
+    async fn set_account(&mut self, username: String) {
+        self.username = username.clone();
+        self.update(StateUpdate::AccountLoaded(username));
+        self.resume_account().await;
+    }
+
+    /// Subscribe to the loaded account and refresh its contacts and groups, then send what waited
+    /// for the connection. The app keeps its state, so reconnecting only needs this part.
+    async fn resume_account(&mut self) {
+        self.subscribe().await;
+        self.get_contact_list().await;
+        self.get_group_list().await;
+
+        self.set_connection(ConnectionState::Connected);
+        self.flush_outbox().await;
+    }
+}
+
+/// Time to wait before reconnecting after `attempts` failed attempts
+pub fn reconnect_delay(attempts: u32) -> Duration {
+    RECONNECT_MIN_DELAY.checked_mul(1 << attempts.min(16))
+        .unwrap_or(RECONNECT_MAX_DELAY)
+        .min(RECONNECT_MAX_DELAY)
+}
+
+/// Attachment metadata of a received message, signald has already downloaded the files
+fn convert_attachments(attachments: &[SignaldAttachment]) -> Vec<Attachment> {
+    attachments.iter().map(|a| {
+        // Files without a name are shown by their stored name
+        let filename = a.custom_filename.clone()
+            .filter(|n| !n.is_empty())
+            .or_else(|| a.stored_filename.as_ref()
+                .and_then(|p| std::path::Path::new(p).file_name())
+                .map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "attachment".to_string());
+
+        Attachment {
+            filename,
+            content_type: a.content_type.clone(),
+            size: a.size,
+            path: a.stored_filename.clone(),
+        }
+    }).collect()
+}
+
+/// A reaction of a data message, sent by `sender`
+fn convert_reaction(reaction: SignaldReaction, sender: String) -> Reaction {
+    Reaction {
+        sender,
+        emoji: reaction.emoji,
+        target_author: reaction.target_author.number.unwrap_or_default(),
+        target_timestamp: reaction.target_sent_timestamp,
+        remove: reaction.remove,
+    }
+}
+
+fn convert_quote(quote: SignaldQuote) -> Quote {
+    Quote {
+        id: quote.id,
+        author: quote.author.number.unwrap_or_default(),
+        text: quote.text,
+    }
+}
+
+/// The current time as a signal timestamp
+fn now_millis() -> i64 {
+    let datetime = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time looped over");
+    datetime.as_millis() as i64
 }
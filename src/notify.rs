@@ -0,0 +1,149 @@
+use std::io;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+const APP_NAME: &str = "signald-tui";
+
+/// A notification about an incoming message
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// Id of the conversation the message belongs to
+    pub conversation: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// Shows notifications to the user
+pub trait Notifier: Send {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()>;
+}
+
+/// The `[notifications]` section of the config
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    /// Command run instead of a desktop notification, `{title}` and `{body}` are replaced
+    pub command: Option<String>,
+    /// Ring the terminal bell when no notification could be shown
+    pub bell: bool,
+    /// Include the message text in notifications
+    pub show_body: bool,
+    /// Conversations that never notify, also see the `toggle-mute` action
+    pub muted: Vec<String>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            command: None,
+            bell: true,
+            show_body: true,
+            muted: Vec::new(),
+        }
+    }
+}
+
+/// Build the notifier for a config: the command hook or a desktop notification,
+/// falling back to ringing `bell`
+pub fn from_config(config: &NotificationConfig, bell: Bell) -> Box<dyn Notifier> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    match &config.command {
+        Some(command) => notifiers.push(Box::new(CommandNotifier { command: command.clone() })),
+        None => notifiers.push(Box::new(DesktopNotifier)),
+    }
+    if config.bell {
+        notifiers.push(Box::new(BellNotifier { bell }));
+    }
+    Box::new(FallbackNotifier { notifiers })
+}
+
+/// Freedesktop notifications over D-Bus
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        notify_rust::Notification::new()
+            .appname(APP_NAME)
+            .summary(&notification.title)
+            .body(&notification.body)
+            .show()
+            .map(|_| ())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+}
+
+/// Runs a command for every notification
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        // Placeholders are replaced per argument, so the message can't inject arguments
+        let args: Vec<String> = self.command.split_whitespace()
+            .map(|a| a.replace("{title}", &notification.title).replace("{body}", &notification.body))
+            .collect();
+        let (program, args) = args.split_first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Empty notification command"))?;
+
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+}
+
+/// A request to ring the terminal bell.
+/// Writing to the terminal from another thread could end up in the middle of a frame,
+/// so the bell is only marked here and rung by the UI between frames.
+#[derive(Clone, Default)]
+pub struct Bell(Arc<AtomicBool>);
+
+impl Bell {
+    pub fn ring(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the bell was rung since the last call
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Rings the terminal bell
+pub struct BellNotifier {
+    pub bell: Bell,
+}
+
+impl Notifier for BellNotifier {
+    fn notify(&mut self, _notification: &Notification) -> io::Result<()> {
+        self.bell.ring();
+        Ok(())
+    }
+}
+
+/// Tries notifiers in order until one succeeds
+pub struct FallbackNotifier {
+    pub notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl Notifier for FallbackNotifier {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        let mut result = Err(io::Error::new(io::ErrorKind::NotFound, "No notifier configured"));
+        for notifier in self.notifiers.iter_mut() {
+            result = notifier.notify(notification);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use super::request::{SignaldRequest, SignaldRequestBuilder};
+use super::response::{ResponseType, SignaldResponse};
+
+/// How long to wait for signald to answer a request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Requests waiting for an answer, by request id
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<SignaldResponse>>>>;
+
+/// A connection to signald.
+///
+/// Responses are read on their own thread. Answers to requests sent with `request` go to the
+/// request, everything else arrives on the receiver returned by `connect_path`.
+pub struct Signald {
+    /// `None` when the socket could not be connected
+    socket: Option<UnixStream>,
+    pending: Pending,
+}
+
+impl Signald {
+    /// Connect to the socket at `path`. The receiver closes when the connection does,
+    /// right away when the socket can't be connected.
+    pub fn connect_path(path: &str) -> (Self, UnboundedReceiver<SignaldResponse>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+
+        let socket = UnixStream::connect(path).ok();
+        if let Some(reader) = socket.as_ref().and_then(|s| s.try_clone().ok()) {
+            let pending = Arc::clone(&pending);
+            thread::spawn(move || read_responses(reader, pending, tx));
+        }
+
+        (Self { socket, pending }, rx)
+    }
+
+    pub fn get_random_id() -> String {
+        format!("{:016x}", rand::random::<u64>())
+    }
+
+    /// Send a request without waiting for the answer
+    pub fn send_request(&mut self, request: &SignaldRequest) -> io::Result<()> {
+        let socket = self.socket.as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        socket.write_all(line.as_bytes())?;
+        socket.flush()
+    }
+
+    /// Send a request and wait for its answer. Requests signald answers with an error fail.
+    pub async fn request(&mut self, mut request: SignaldRequest) -> io::Result<SignaldResponse> {
+        let id = request.id.get_or_insert_with(Signald::get_random_id).clone();
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), tx);
+
+        if let Err(e) = self.send_request(&request) {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        let response = match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(response)) => response,
+            // The connection closed before the answer arrived
+            Ok(Err(_)) => return Err(io::ErrorKind::ConnectionAborted.into()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+        };
+
+        match &response.data {
+            ResponseType::Error(error, data) => Err(io::Error::new(io::ErrorKind::Other, format!("{}: {}", error, data))),
+            _ => Ok(response),
+        }
+    }
+
+    pub async fn list_accounts(&mut self) -> io::Result<SignaldResponse> {
+        self.request(SignaldRequestBuilder::new()
+            .set_type("list_accounts".to_string())
+            .build()).await
+    }
+
+    pub async fn list_contacts(&mut self, username: String) -> io::Result<SignaldResponse> {
+        self.request(SignaldRequestBuilder::new()
+            .set_type("list_contacts".to_string())
+            .set_username(username)
+            .build()).await
+    }
+
+    pub async fn list_groups(&mut self, username: String) -> io::Result<SignaldResponse> {
+        self.request(SignaldRequestBuilder::new()
+            .set_type("list_groups".to_string())
+            .set_username(username)
+            .build()).await
+    }
+
+    /// Receive the messages of `username` as events
+    pub async fn subscribe(&mut self, username: String) -> io::Result<SignaldResponse> {
+        self.request(SignaldRequestBuilder::new()
+            .set_type("subscribe".to_string())
+            .set_username(username)
+            .build()).await
+    }
+}
+
+impl Drop for Signald {
+    /// Closing the socket ends the reader thread
+    fn drop(&mut self) {
+        if let Some(socket) = &self.socket {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Read responses until the connection closes, lines that aren't json responses are skipped.
+/// Requests still waiting when it closes fail.
+fn read_responses(socket: UnixStream, pending: Pending, tx: UnboundedSender<SignaldResponse>) {
+    for line in BufReader::new(socket).lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let response: SignaldResponse = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        let waiting = response.id.as_ref().and_then(|id| pending.lock().unwrap().remove(id));
+        match waiting {
+            Some(request) => {
+                let _ = request.send(response);
+            }
+            None => {
+                if tx.send(response).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    pending.lock().unwrap().clear();
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use super::response::Address;
+
+/// A request to signald, serialized as one line of json
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignaldRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient_group_id: Option<String>,
+    /// Signald stamps messages sent without a timestamp itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<RequestQuote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<RequestReaction>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<RequestAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestQuote {
+    pub id: i64,
+    pub author: Address,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestReaction {
+    pub emoji: String,
+    pub remove: bool,
+    pub target_author: Address,
+    pub target_sent_timestamp: i64,
+}
+
+/// A file to upload, signald reads it from `filename`
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestAttachment {
+    pub filename: String,
+}
+
+#[derive(Default)]
+pub struct SignaldRequestBuilder {
+    request: SignaldRequest,
+}
+
+impl SignaldRequestBuilder {
+    pub fn new() -> Self {
+        Self { request: SignaldRequest::default() }
+    }
+
+    pub fn set_type(mut self, request_type: String) -> Self {
+        self.request.request_type = request_type;
+        self
+    }
+
+    pub fn set_id(mut self, id: String) -> Self {
+        self.request.id = Some(id);
+        self
+    }
+
+    pub fn set_username(mut self, username: String) -> Self {
+        self.request.username = Some(username);
+        self
+    }
+
+    pub fn set_message_body(mut self, message_body: Option<String>) -> Self {
+        self.request.message_body = message_body;
+        self
+    }
+
+    pub fn set_recipient_number(mut self, number: String) -> Self {
+        self.request.recipient_number = Some(number);
+        self
+    }
+
+    pub fn set_recipient_group_id(mut self, group_id: String) -> Self {
+        self.request.recipient_group_id = Some(group_id);
+        self
+    }
+
+    pub fn set_timestamp(mut self, timestamp: i64) -> Self {
+        self.request.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Reply to the message `author` sent at `id`
+    pub fn set_quote(mut self, id: i64, author: String, text: String) -> Self {
+        self.request.quote = Some(RequestQuote { id, author: Address::new(author), text });
+        self
+    }
+
+    /// React to the message `target_author` sent at `target_timestamp`, or take the reaction back
+    pub fn set_reaction(mut self, emoji: String, remove: bool, target_author: String, target_timestamp: i64) -> Self {
+        self.request.reaction = Some(RequestReaction {
+            emoji,
+            remove,
+            target_author: Address::new(target_author),
+            target_sent_timestamp: target_timestamp,
+        });
+        self
+    }
+
+    /// Attach the files at `paths`
+    pub fn set_attachments(mut self, paths: Vec<String>) -> Self {
+        self.request.attachments = paths.into_iter()
+            .map(|filename| RequestAttachment { filename })
+            .collect();
+        self
+    }
+
+    pub fn build(self) -> SignaldRequest {
+        self.request
+    }
+}
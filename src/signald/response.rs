@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A line sent by signald, either the answer to a request or an event such as an incoming message
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "RawResponse")]
+pub struct SignaldResponse {
+    /// The id of the request answered, `None` for events
+    pub id: Option<String>,
+    pub data: ResponseType,
+}
+
+/// The data of a response, `None` when it doesn't have the expected shape
+#[derive(Debug, Clone)]
+pub enum ResponseType {
+    Message(Option<Box<Message>>),
+    ContactList(Option<Vec<Contact>>),
+    AccountList(Option<AccountList>),
+    GroupList(Option<GroupList>),
+    Subscribed,
+    Unsubscribed,
+    Success,
+    /// A failed request, with the error type
+    Error(String, Value),
+    Unknown(String, Value),
+}
+
+#[derive(Deserialize)]
+struct RawResponse {
+    #[serde(rename = "type")]
+    response_type: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    data: Value,
+}
+
+impl From<RawResponse> for SignaldResponse {
+    fn from(raw: RawResponse) -> Self {
+        let RawResponse { response_type, id, data } = raw;
+        let data = match response_type.as_str() {
+            "message" => ResponseType::Message(serde_json::from_value(data).ok()),
+            "contact_list" => ResponseType::ContactList(serde_json::from_value(data).ok()),
+            "account_list" => ResponseType::AccountList(serde_json::from_value(data).ok()),
+            "group_list" => ResponseType::GroupList(serde_json::from_value(data).ok()),
+            "subscribed" => ResponseType::Subscribed,
+            "unsubscribed" => ResponseType::Unsubscribed,
+            "success" => ResponseType::Success,
+            t if t.ends_with("error") => ResponseType::Error(response_type, data),
+            _ => ResponseType::Unknown(response_type, data),
+        };
+        SignaldResponse { id, data }
+    }
+}
+
+/// A signal user, identified by phone number
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Address {
+    #[serde(default)]
+    pub number: Option<String>,
+}
+
+impl Address {
+    pub fn new(number: String) -> Self {
+        Address { number: Some(number) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Contact {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub number: String,
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountList {
+    pub accounts: Vec<Account>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Account {
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupList {
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Group {
+    pub group_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub members: Option<Vec<String>>,
+}
+
+/// An incoming message, receipt, typing indicator or message sent from another device
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub timestamp: i64,
+    #[serde(default)]
+    pub data_message: Option<DataMessage>,
+    #[serde(default)]
+    pub sync_message: Option<SyncMessage>,
+    #[serde(default)]
+    pub receipt: Option<Receipt>,
+    #[serde(default)]
+    pub typing: Option<Typing>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataMessage {
+    pub timestamp: i64,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub group_info: Option<GroupInfo>,
+    #[serde(default)]
+    pub quote: Option<Quote>,
+    #[serde(default)]
+    pub reaction: Option<Reaction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncMessage {
+    #[serde(default)]
+    pub sent: Option<SentMessage>,
+}
+
+/// A message sent from another device of the account
+#[derive(Debug, Clone, Deserialize)]
+pub struct SentMessage {
+    /// The recipient, absent for group messages
+    #[serde(default)]
+    pub destination: String,
+    pub timestamp: i64,
+    pub message: DataMessage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Receipt {
+    /// Either "DELIVERY" or "READ"
+    pub r#type: String,
+    /// The timestamps of the messages received or read
+    #[serde(default)]
+    pub timestamps: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Typing {
+    /// Either "STARTED" or "STOPPED"
+    pub action: String,
+    #[serde(default)]
+    pub group_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupInfo {
+    pub group_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub members: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Where signald stored the downloaded file
+    #[serde(default)]
+    pub stored_filename: Option<String>,
+    /// The name the sender gave the file
+    #[serde(default)]
+    pub custom_filename: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Quote {
+    pub id: i64,
+    pub author: Address,
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reaction {
+    pub emoji: String,
+    #[serde(default)]
+    pub remove: bool,
+    pub target_author: Address,
+    pub target_sent_timestamp: i64,
+}
@@ -0,0 +1,272 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::{DeliveryStatus, Message, Reaction};
+
+const APP_NAME: &str = "signald-tui";
+const LOG_EXTENSION: &str = "jsonl";
+const REACTIONS_EXTENSION: &str = "reactions.jsonl";
+const STATUS_EXTENSION: &str = "status.jsonl";
+const DRAFTS_FILE: &str = "drafts.json";
+const MUTED_FILE: &str = "muted.json";
+
+/// Persistent message history.
+///
+/// Every conversation is stored as an append-only log of json encoded messages, one per line,
+/// in `<data dir>/signald-tui/<account>/<conversation>.jsonl`.
+/// Reactions are logged the same way in `<conversation>.reactions.jsonl`, and status changes of
+/// sent messages in `<conversation>.status.jsonl`. Status changes are applied when loading,
+/// so receipts never rewrite the message log.
+///
+/// The logs are only read once: where the lines of a message log start and the statuses are
+/// kept, later reads start where the previous one ended.
+pub struct MessageStore {
+    dir: PathBuf,
+    /// Line offsets of the message logs, by conversation
+    indexes: RefCell<HashMap<String, LogIndex>>,
+    /// Latest statuses of the status logs, by conversation
+    statuses: RefCell<HashMap<String, StatusLog>>,
+}
+
+/// Where the lines of a message log start, for the first `len` bytes
+#[derive(Default)]
+struct LogIndex {
+    len: u64,
+    lines: Vec<u64>,
+}
+
+/// The latest status of every message, for the first `len` bytes of a status log
+#[derive(Default)]
+struct StatusLog {
+    len: u64,
+    statuses: HashMap<(String, i64), DeliveryStatus>,
+}
+
+/// Lines read from a log, with the offsets they start at
+type Lines = Vec<(u64, Vec<u8>)>;
+
+/// A logged status change of a message
+#[derive(Serialize, Deserialize)]
+struct StatusChange {
+    sender: String,
+    timestamp: i64,
+    status: DeliveryStatus,
+}
+
+impl MessageStore {
+    /// Open the store for an account in `data_dir`, or in the XDG data dir when `None`
+    pub fn open(data_dir: Option<PathBuf>, account: &str) -> io::Result<MessageStore> {
+        let base = match data_dir {
+            Some(d) => d,
+            None => dirs::data_dir()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory found"))?
+                .join(APP_NAME),
+        };
+        let dir = base.join(file_name(account));
+        fs::create_dir_all(&dir)?;
+        Ok(MessageStore {
+            dir,
+            indexes: RefCell::new(HashMap::new()),
+            statuses: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Append a message to the log of a conversation
+    pub fn append(&self, conversation: &str, message: &Message) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(conversation))?;
+
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    /// Load at most `count` messages of a conversation, skipping the `skip` most recent ones.
+    /// Messages are returned oldest first.
+    pub fn load_page(&self, conversation: &str, skip: usize, count: usize) -> io::Result<Vec<Message>> {
+        let mut file = match File::open(self.log_path(conversation)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let (from, to) = {
+            let mut indexes = self.indexes.borrow_mut();
+            let index = indexes.entry(conversation.to_string()).or_default();
+            index.update(&mut file)?;
+
+            let end = index.lines.len().saturating_sub(skip);
+            let start = end.saturating_sub(count);
+            if start == end {
+                return Ok(Vec::new());
+            }
+            (index.lines[start], index.lines.get(end).cloned().unwrap_or(index.len))
+        };
+
+        let mut page = vec![0; (to - from) as usize];
+        file.seek(SeekFrom::Start(from))?;
+        file.read_exact(&mut page)?;
+
+        // Skip lines that can't be parsed, e.g. a line cut off by a crash
+        let mut messages: Vec<Message> = page.split(|b| *b == b'\n')
+            .filter_map(|l| serde_json::from_slice(l).ok())
+            .collect();
+
+        self.apply_statuses(conversation, &mut messages)?;
+        Ok(messages)
+    }
+
+    /// Log a status change of a stored message, it is applied when the message is loaded
+    pub fn append_status(&self, conversation: &str, sender: &str, timestamp: i64, status: DeliveryStatus) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.status_path(conversation))?;
+
+        let change = StatusChange {
+            sender: sender.to_string(),
+            timestamp,
+            status,
+        };
+        let mut line = serde_json::to_string(&change)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    /// Apply the latest logged status of each message
+    fn apply_statuses(&self, conversation: &str, messages: &mut [Message]) -> io::Result<()> {
+        let mut file = match File::open(self.status_path(conversation)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let mut logs = self.statuses.borrow_mut();
+        let log = logs.entry(conversation.to_string()).or_default();
+        if file.metadata()?.len() < log.len {
+            *log = StatusLog::default();
+        }
+        let (lines, end) = read_lines(&mut file, log.len)?;
+        for (_, line) in lines {
+            if let Ok(change) = serde_json::from_slice::<StatusChange>(&line) {
+                log.statuses.insert((change.sender, change.timestamp), change.status);
+            }
+        }
+        log.len = end;
+
+        for message in messages.iter_mut() {
+            if let Some(status) = log.statuses.get(&(message.sender.clone(), message.timestamp)) {
+                message.status = *status;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a reaction to the reaction log of a conversation
+    pub fn append_reaction(&self, conversation: &str, reaction: &Reaction) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.reactions_path(conversation))?;
+
+        let mut line = serde_json::to_string(reaction)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+    }
+
+    /// All logged reactions of a conversation, oldest first
+    pub fn load_reactions(&self, conversation: &str) -> io::Result<Vec<Reaction>> {
+        let file = match File::open(self.reactions_path(conversation)) {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(BufReader::new(file)
+            .lines()
+            .filter_map(|l| serde_json::from_str(&l.ok()?).ok())
+            .collect())
+    }
+
+    /// Load the unsent drafts, keyed by conversation
+    pub fn load_drafts(&self) -> io::Result<HashMap<String, String>> {
+        match fs::read_to_string(self.dir.join(DRAFTS_FILE)) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save_drafts(&self, drafts: &HashMap<String, String>) -> io::Result<()> {
+        fs::write(self.dir.join(DRAFTS_FILE), serde_json::to_string(drafts)?)
+    }
+
+    /// Load the ids of the muted conversations
+    pub fn load_muted(&self) -> io::Result<Vec<String>> {
+        match fs::read_to_string(self.dir.join(MUTED_FILE)) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save_muted(&self, muted: &[String]) -> io::Result<()> {
+        fs::write(self.dir.join(MUTED_FILE), serde_json::to_string(muted)?)
+    }
+
+    fn log_path(&self, conversation: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", file_name(conversation), LOG_EXTENSION))
+    }
+
+    fn reactions_path(&self, conversation: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", file_name(conversation), REACTIONS_EXTENSION))
+    }
+
+    fn status_path(&self, conversation: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", file_name(conversation), STATUS_EXTENSION))
+    }
+}
+
+impl LogIndex {
+    /// Index the lines written since the last update, or the whole log again when it shrank
+    fn update(&mut self, file: &mut File) -> io::Result<()> {
+        if file.metadata()?.len() < self.len {
+            *self = LogIndex::default();
+        }
+        let (lines, end) = read_lines(file, self.len)?;
+        self.lines.extend(lines.into_iter().map(|(offset, _)| offset));
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Read the complete lines after `offset`, with the offsets they start at, and where the last one ends.
+/// A line without a line break is still being written and left for the next read.
+fn read_lines(file: &mut File, offset: u64) -> io::Result<(Lines, u64)> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+    let mut lines = Vec::new();
+    let mut end = offset;
+    loop {
+        let mut line = Vec::new();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 || line.last() != Some(&b'\n') {
+            break;
+        }
+        lines.push((end, line));
+        end += read as u64;
+    }
+    Ok((lines, end))
+}
+
+/// Make an identifier safe to use as a file name
+fn file_name(id: &str) -> String {
+    id.replace('/', "_")
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+
+use chrono::{Date, Local, TimeZone};
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+use tui::Frame;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::app::{App, ChatFrame, DeliveryStatus, Message, View};
+use crate::attachment::Attachment;
+use crate::ui::image::{ImagePlacement, ImageProtocol, Preview};
+
+/// Indentation of the message body below the sender line
+const BODY_INDENT: &str = "  ";
+/// Marks the lines of a quoted message
+const QUOTE_PREFIX: &str = "│ ";
+/// Maximum amount of lines shown of a quoted message
+const QUOTE_LINES: usize = 2;
+
+/// A single terminal row, made of differently styled parts
+type Line = Vec<Text<'static>>;
+
+/// A rendered row of the chat
+struct Row {
+    text: Line,
+    /// Path of the image preview starting on this row
+    image: Option<String>,
+}
+
+impl Row {
+    fn new(text: Line) -> Row {
+        Row { text, image: None }
+    }
+}
+
+pub fn draw_chat<B>(f: &mut Frame<B>, app: &mut App, area: Rect)
+    where B: Backend,
+{
+    let width = area.width.saturating_sub(2) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+    app.image_placements.clear();
+
+    // Resolve sender names before borrowing the conversation
+    let username = app.username.clone();
+    let names: HashMap<String, (String, Option<String>)> = app.contacts.iter()
+        .map(|c| (c.number.clone(), (c.display_name(), c.color.clone())))
+        .collect();
+    let show_images = app.config.images.enabled;
+    let protocol = app.image_protocol;
+    let selected_message = app.selected_message;
+    let focused = app.focused_view == View::Messages;
+
+    let selected = app.get_selected_conversation_id();
+    let conv = match selected.as_ref().and_then(|id| app.conversations.get(id)) {
+        Some(c) => c,
+        None => {
+            Paragraph::new([Text::raw("No conversation selected")].iter())
+                .block(Block::default()
+                    .borders(Borders::ALL)
+                )
+                .render(f, area);
+            return;
+        }
+    };
+
+    let mut title = match conv.new_below {
+        0 if conv.scroll > 0 => "Chat (scrolled up)".to_string(),
+        0 => "Chat".to_string(),
+        1 => "Chat (1 new message below)".to_string(),
+        n => format!("Chat ({} new messages below)", n),
+    };
+    let typing: Vec<String> = conv.typing_members().iter()
+        .map(|m| names.get(*m).map_or(m.to_string(), |(name, _)| name.clone()))
+        .collect();
+    match typing.as_slice() {
+        [] => {}
+        [name] => title.push_str(&format!(" - {} is typing…", name)),
+        [first, second] => title.push_str(&format!(" - {} and {} are typing…", first, second)),
+        _ => title.push_str(" - Several people are typing…"),
+    }
+
+    let messages = &conv.messages;
+    let previews = &app.image_previews;
+    // Images are decoded by the network, drawing only uses the decoded previews
+    let mut missing: Vec<String> = Vec::new();
+    let mut render = |i: usize| {
+        let previous = if i > 0 { messages.get(i - 1) } else { None };
+        let view = MessageView {
+            names: &names,
+            username: &username,
+            width,
+            reactions: conv.reaction_counts(&messages[i]),
+            selected: selected_message == Some(i),
+        };
+        if !show_images {
+            return message_lines(&messages[i], previous, &view, None);
+        }
+
+        for attachment in messages[i].attachments.iter().filter(|a| is_image(a)) {
+            if let Some(path) = &attachment.path {
+                if !previews.contains_key(path) {
+                    missing.push(path.clone());
+                }
+            }
+        }
+        message_lines(&messages[i], previous, &view, Some(previews))
+    };
+
+    // Messages are rendered once, both to measure and to draw them
+    let mut rendered: HashMap<usize, Vec<Row>> = HashMap::new();
+    let window = chat_window(messages.len(), conv.scroll, height, |i| {
+        rendered.entry(i).or_insert_with(|| render(i)).len()
+    });
+    let mut rows: Vec<Row> = Vec::new();
+    for i in window.start..window.end {
+        rows.append(&mut rendered.remove(&i).unwrap_or_else(|| render(i)));
+    }
+    if window.scroll == window.max_scroll {
+        rows.truncate(height);
+    } else {
+        let skip = rows.len().saturating_sub(height);
+        rows.drain(..skip);
+    }
+
+    // Graphics protocols draw over the half blocks of previews that are entirely visible
+    let mut placements = Vec::new();
+    if protocol == ImageProtocol::Sixel || protocol == ImageProtocol::Kitty {
+        for (i, row) in rows.iter().enumerate() {
+            let path = match &row.image {
+                Some(p) => p,
+                None => continue,
+            };
+            let preview_rows = previews.get(path)
+                .and_then(|p| p.as_ref())
+                .map_or(0, |p| p.rows as usize);
+            if i + preview_rows <= rows.len() {
+                placements.push(ImagePlacement {
+                    path: path.clone(),
+                    x: area.x + 1 + BODY_INDENT.len() as u16,
+                    y: area.y + 1 + i as u16,
+                });
+            }
+        }
+    }
+
+    let mut text: Vec<Text> = Vec::new();
+    for (i, mut row) in rows.into_iter().enumerate() {
+        if i > 0 {
+            text.push(Text::raw("\n"));
+        }
+        text.append(&mut row.text);
+    }
+
+    Paragraph::new(text.iter())
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(super::border_style(focused))
+            .title(&title)
+        )
+        .render(f, area);
+
+    app.image_placements = placements;
+    app.request_previews(missing);
+    app.chat_frame = ChatFrame {
+        shown: window.end - window.start,
+        max_scroll: window.max_scroll,
+    };
+    if let Some(conv) = selected.and_then(|id| app.conversations.get_mut(&id)) {
+        conv.scroll = window.scroll;
+        conv.new_below = conv.new_below.min(conv.scroll);
+    }
+}
+
+/// The messages filling a chat pane
+#[derive(Debug, PartialEq)]
+pub struct ChatWindow {
+    /// Index of the first message shown, at least partly
+    pub start: usize,
+    /// Index after the last message shown
+    pub end: usize,
+    /// The scroll, limited to `max_scroll`
+    pub scroll: usize,
+    /// Largest scroll that still fills the pane, scrolled further only empty rows would be added
+    pub max_scroll: usize,
+}
+
+/// Find the messages that fill a pane of `height` rows when scrolled up `scroll` messages,
+/// with `rows(i)` the amount of rows of message `i`.
+/// The pane is filled upwards from the last visible message. When scrolled to the top,
+/// it is filled downwards from the first message instead so it stays reachable.
+pub fn chat_window<F>(len: usize, scroll: usize, height: usize, mut rows: F) -> ChatWindow
+    where F: FnMut(usize) -> usize,
+{
+    // Messages at the top that fit in the pane entirely
+    let mut used = 0;
+    let mut fit = 0;
+    while fit < len {
+        let message_rows = rows(fit);
+        if used + message_rows > height {
+            break;
+        }
+        used += message_rows;
+        fit += 1;
+    }
+    let max_scroll = len.saturating_sub(fit.max(1));
+    let scroll = scroll.min(max_scroll);
+
+    if scroll == max_scroll {
+        let mut used = 0;
+        let mut end = 0;
+        while end < len && used < height {
+            used += rows(end);
+            end += 1;
+        }
+        return ChatWindow { start: 0, end, scroll, max_scroll };
+    }
+
+    let end = len - scroll;
+    let mut used = 0;
+    let mut start = end;
+    while start > 0 && used < height {
+        start -= 1;
+        used += rows(start);
+    }
+    ChatWindow { start, end, scroll, max_scroll }
+}
+
+/// What message lines are rendered with besides the message itself
+struct MessageView<'a> {
+    names: &'a HashMap<String, (String, Option<String>)>,
+    username: &'a str,
+    width: usize,
+    /// Reactions to the message as emoji and count
+    reactions: Vec<(String, usize)>,
+    selected: bool,
+}
+
+/// Render a message as a sender line followed by the wrapped body and reactions,
+/// preceded by a date separator when it is the first message of a day.
+/// Image attachments are drawn when their `previews` are given.
+fn message_lines(
+    message: &Message,
+    previous: Option<&Message>,
+    view: &MessageView,
+    previews: Option<&HashMap<String, Option<Preview>>>,
+) -> Vec<Row> {
+    let mut lines: Vec<Row> = Vec::new();
+    let width = view.width;
+
+    let date = Local.timestamp_millis(message.timestamp);
+    if previous.map_or(true, |p| day(p.timestamp) != date.date()) {
+        lines.push(Row::new(date_separator(date.date(), width)));
+    }
+
+    let own = message.sender == view.username;
+    let (name, color) = match view.names.get(&message.sender) {
+        _ if own => ("Me".to_string(), Color::Green),
+        Some((name, color)) => (name.clone(), contact_color(color.as_ref())),
+        None => (message.sender.clone(), Color::Reset),
+    };
+
+    let mut name_style = Style::default().fg(color).modifier(Modifier::BOLD);
+    if view.selected {
+        name_style = name_style.modifier(Modifier::BOLD | Modifier::REVERSED);
+    }
+    let mut header: Line = vec![
+        Text::styled(date.format("%H:%M ").to_string(), Style::default().fg(Color::DarkGray)),
+        Text::styled(name, name_style),
+    ];
+    if own {
+        let style = match message.status {
+            DeliveryStatus::Failed => Style::default().fg(Color::Red),
+            DeliveryStatus::Read => Style::default().fg(Color::Blue),
+            _ => Style::default().fg(Color::DarkGray),
+        };
+        let indicator = match message.status {
+            DeliveryStatus::Sending if !message.attachments.is_empty() => "uploading…",
+            status => status_indicator(status),
+        };
+        header.push(Text::styled(format!(" {}", indicator), style));
+    }
+    lines.push(Row::new(header));
+
+    let body_width = width.saturating_sub(BODY_INDENT.len());
+    if let Some(quote) = &message.quote {
+        let author = if quote.author == view.username {
+            "Me".to_string()
+        } else {
+            view.names.get(&quote.author).map_or(quote.author.clone(), |(name, _)| name.clone())
+        };
+        let style = Style::default().fg(Color::DarkGray);
+        lines.push(Row::new(vec![Text::styled(format!("{}{}{}", BODY_INDENT, QUOTE_PREFIX, author), style.modifier(Modifier::BOLD))]));
+        let quoted = wrap(&quote.text, body_width.saturating_sub(QUOTE_PREFIX.len()));
+        for (i, line) in quoted.iter().enumerate().take(QUOTE_LINES) {
+            // Long quotes are cut off
+            let ellipsis = if i + 1 == QUOTE_LINES && quoted.len() > QUOTE_LINES { "…" } else { "" };
+            lines.push(Row::new(vec![Text::styled(format!("{}{}{}{}", BODY_INDENT, QUOTE_PREFIX, line, ellipsis), style)]));
+        }
+    }
+    for attachment in message.attachments.iter() {
+        for line in wrap(&format!("📎 {}", attachment.summary()), body_width) {
+            lines.push(Row::new(vec![Text::styled(format!("{}{}", BODY_INDENT, line), Style::default().fg(Color::Cyan))]));
+        }
+
+        let preview = attachment.path.as_ref()
+            .and_then(|path| previews?.get(path)?.as_ref().map(|p| (path, p)));
+        if let Some((path, preview)) = preview {
+            for (i, mut blocks) in preview.half_block_lines().into_iter().enumerate() {
+                let mut text = vec![Text::raw(BODY_INDENT)];
+                text.append(&mut blocks);
+                lines.push(Row {
+                    text,
+                    image: if i == 0 { Some(path.clone()) } else { None },
+                });
+            }
+        }
+    }
+    // Messages with only an attachment have no body
+    if !message.message.is_empty() || message.attachments.is_empty() {
+        for line in wrap(&message.message, body_width) {
+            lines.push(Row::new(vec![Text::raw(format!("{}{}", BODY_INDENT, line))]));
+        }
+    }
+
+    if !view.reactions.is_empty() {
+        let counts: Vec<String> = view.reactions.iter()
+            .map(|(emoji, count)| format!("{} {}", emoji, count))
+            .collect();
+        for line in wrap(&counts.join("  "), body_width) {
+            lines.push(Row::new(vec![Text::styled(format!("{}{}", BODY_INDENT, line), Style::default().fg(Color::DarkGray))]));
+        }
+    }
+
+    lines
+}
+
+fn is_image(attachment: &Attachment) -> bool {
+    attachment.content_type.as_ref().map_or(false, |t| t.starts_with("image/"))
+}
+
+fn day(timestamp: i64) -> Date<Local> {
+    Local.timestamp_millis(timestamp).date()
+}
+
+fn date_separator(date: Date<Local>, width: usize) -> Line {
+    let label = format!(" {} ", date.format("%A %-d %B %Y"));
+    let side = width.saturating_sub(label.width()) / 2;
+    let rule = "─".repeat(side);
+    vec![Text::styled(format!("{}{}{}", rule, label, rule), Style::default().fg(Color::DarkGray))]
+}
+
+/// Check marks shown after an own message
+fn status_indicator(status: DeliveryStatus) -> &'static str {
+    match status {
+        DeliveryStatus::Failed => "✗ not sent",
+        DeliveryStatus::Received => "",
+        DeliveryStatus::Sending => "…",
+        DeliveryStatus::Sent => "✓",
+        DeliveryStatus::Delivered => "✓✓",
+        DeliveryStatus::Read => "✓✓",
+    }
+}
+
+/// Terminal color for a signal contact color
+fn contact_color(color: Option<&String>) -> Color {
+    match color.map(|c| c.as_str()) {
+        Some("red") => Color::Red,
+        Some("pink") | Some("purple") | Some("deep_purple") => Color::Magenta,
+        Some("indigo") | Some("blue") | Some("ultramarine") => Color::Blue,
+        Some("light_blue") | Some("cyan") | Some("teal") => Color::Cyan,
+        Some("green") | Some("light_green") => Color::Green,
+        Some("orange") | Some("deep_orange") | Some("amber") | Some("yellow") => Color::Yellow,
+        Some("grey") | Some("blue_grey") | Some("brown") => Color::Gray,
+        _ => Color::Reset,
+    }
+}
+
+/// Word wrap text to lines of at most `width` columns.
+/// Words longer than a line are broken, existing line breaks are kept.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+
+        for word in paragraph.split(' ') {
+            let word_width = word.width();
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::replace(&mut line, String::new()));
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+
+            for c in word.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if line_width > 0 && line_width + char_width > width {
+                    lines.push(std::mem::replace(&mut line, String::new()));
+                    line_width = 0;
+                }
+                line.push(c);
+                line_width += char_width;
+            }
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
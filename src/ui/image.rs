@@ -0,0 +1,203 @@
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, RgbImage};
+use serde::Deserialize;
+use tui::style::{Color, Style};
+use tui::widgets::Text;
+
+/// Assumed size of a terminal cell in pixels, used to scale images for graphics protocols
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+/// Maximum size of a kitty graphics payload chunk
+const KITTY_CHUNK: usize = 4096;
+
+/// How images are drawn in the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProtocol {
+    /// Pick the best protocol the terminal supports
+    Auto,
+    /// Unicode half blocks, works in every terminal with true color
+    HalfBlocks,
+    Sixel,
+    Kitty,
+}
+
+impl ImageProtocol {
+    /// Resolve `Auto` from the environment.
+    /// Querying the terminal would interfere with the input, so this relies on the variables terminals set.
+    pub fn detect(self) -> ImageProtocol {
+        if self != ImageProtocol::Auto {
+            return self;
+        }
+
+        let term = env::var("TERM").unwrap_or_default();
+        let program = env::var("TERM_PROGRAM").unwrap_or_default();
+        if env::var("KITTY_WINDOW_ID").is_ok() || term == "xterm-kitty" {
+            ImageProtocol::Kitty
+        } else if term.contains("sixel") || term.starts_with("foot") || term == "mlterm"
+            || program == "WezTerm" {
+            ImageProtocol::Sixel
+        } else {
+            ImageProtocol::HalfBlocks
+        }
+    }
+}
+
+/// A downscaled image attachment, ready to be drawn in the chat
+pub struct Preview {
+    /// Size in terminal cells
+    pub columns: u16,
+    pub rows: u16,
+    image: DynamicImage,
+    /// Pixels for half block rendering, two per cell stacked vertically
+    half_blocks: RgbImage,
+}
+
+impl Preview {
+    /// Load an image file and scale it to fit in `max_columns` by `max_rows` cells.
+    /// Returns `None` when the file can't be decoded.
+    pub fn load(path: &str, max_columns: u16, max_rows: u16) -> Option<Preview> {
+        let image = image::open(path).ok()?;
+        Some(Preview::from_image(image, max_columns, max_rows))
+    }
+
+    pub fn from_image(image: DynamicImage, max_columns: u16, max_rows: u16) -> Preview {
+        let max_columns = u32::from(max_columns.max(1));
+        let max_rows = u32::from(max_rows.max(1));
+
+        // Never scale small images up
+        let half_blocks = if image.width() <= max_columns && image.height() <= max_rows * 2 {
+            image.to_rgb()
+        } else {
+            image.resize(max_columns, max_rows * 2, FilterType::Triangle).to_rgb()
+        };
+        let columns = half_blocks.width() as u16;
+        let rows = ((half_blocks.height() + 1) / 2) as u16;
+
+        let image = image.resize(
+            u32::from(columns) * CELL_WIDTH,
+            u32::from(rows) * CELL_HEIGHT,
+            FilterType::Triangle,
+        );
+
+        Preview { columns, rows, image, half_blocks }
+    }
+
+    /// The image as rows of "▀" characters, the top pixel is the foreground and the bottom one the background
+    pub fn half_block_lines(&self) -> Vec<Vec<Text<'static>>> {
+        let (width, height) = self.half_blocks.dimensions();
+        (0..height).step_by(2).map(|y| {
+            (0..width).map(|x| {
+                let top = self.half_blocks.get_pixel(x, y);
+                let mut style = Style::default().fg(Color::Rgb(top[0], top[1], top[2]));
+                if y + 1 < height {
+                    let bottom = self.half_blocks.get_pixel(x, y + 1);
+                    style = style.bg(Color::Rgb(bottom[0], bottom[1], bottom[2]));
+                }
+                Text::styled("▀", style)
+            }).collect()
+        }).collect()
+    }
+
+    /// Escape sequence drawing the image at the cursor position
+    pub fn encode(&self, protocol: ImageProtocol) -> Option<String> {
+        match protocol {
+            ImageProtocol::Sixel => Some(encode_sixel(&self.image.to_rgb())),
+            ImageProtocol::Kitty => Some(encode_kitty(&self.image, self.columns, self.rows)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for Preview {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Preview")
+            .field("columns", &self.columns)
+            .field("rows", &self.rows)
+            .finish()
+    }
+}
+
+/// An image drawn with a graphics protocol after the frame is rendered
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImagePlacement {
+    /// Attachment path, the key of the preview
+    pub path: String,
+    pub x: u16,
+    pub y: u16,
+}
+
+/// Encode an image as sixels, using a 6x6x6 color cube as palette
+pub fn encode_sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+
+    for i in 0..216 {
+        let (r, g, b) = (i / 36, (i / 6) % 6, i % 6);
+        out.push_str(&format!("#{};2;{};{};{}", i, r * 20, g * 20, b * 20));
+    }
+
+    let color = |x: u32, y: u32| {
+        let p = image.get_pixel(x, y);
+        let level = |c: u8| (u32::from(c) * 5 + 127) / 255;
+        (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize
+    };
+
+    for band in (0..height).step_by(6) {
+        let band_height = (height - band).min(6);
+        let mut used = [false; 216];
+        for y in band..band + band_height {
+            for x in 0..width {
+                used[color(x, y)] = true;
+            }
+        }
+
+        // Every color of the band is drawn in its own pass over the same row
+        for (c, _) in used.iter().enumerate().filter(|(_, u)| **u) {
+            out.push_str(&format!("#{}", c));
+            for x in 0..width {
+                let bits = (0..band_height)
+                    .filter(|dy| color(x, band + dy) == c)
+                    .fold(0u8, |bits, dy| bits | 1 << dy);
+                out.push((63 + bits) as char);
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Encode an image for the kitty graphics protocol, scaled to `columns` by `rows` cells
+pub fn encode_kitty(image: &DynamicImage, columns: u16, rows: u16) -> String {
+    let rgba = image.to_rgba();
+    let (width, height) = rgba.dimensions();
+    let data = base64::encode(rgba.into_raw());
+
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=32,s={},v={},c={},r={},C=1,q=2,m={};{}\x1b\\",
+                width, height, columns, rows, more, chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out
+}
+
+/// Remove all images drawn with the kitty protocol
+pub fn clear_kitty_images<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(b"\x1b_Ga=d,q=2\x1b\\")
+}
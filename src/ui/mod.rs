@@ -1,44 +1,199 @@
+use std::time::Instant;
+
 use tui::backend::Backend;
 use tui::Frame;
-use tui::layout::{Constraint, Direction, Layout};
+use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::widgets::{Block, Borders, List, Paragraph, Text, Widget};
 
-use crate::app::{App, Conversation, Point};
-use chrono::{NaiveDateTime, Local, TimeZone};
-use tui::style::{Style, Color};
+use crate::app::{App, ConnectionState, Conversation, NoticeKind, Point, View};
+use tui::style::{Style, Color, Modifier};
+
+pub mod chat;
+pub mod image;
+
+/// Maximum amount of lines the input box grows to
+const MAX_INPUT_HEIGHT: usize = 8;
+/// Maximum amount of paths shown by the attachment picker
+const MAX_COMPLETIONS: usize = 8;
 
 pub fn draw_basic_view<B>(f: &mut Frame<B>, app: &mut App)
     where B: Backend,
 {
-    let size = f.size();
+    if let View::Accounts = app.focused_view {
+        draw_account_picker(f, app);
+        return;
+    }
+    if let View::Errors = app.focused_view {
+        draw_error_log(f, app);
+        return;
+    }
+
+    let screen = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(1)
+        ].as_ref())
+        .split(f.size());
+    let size = screen[0];
+    let focused = app.focused_view;
 
+    // The sidebar never takes more than half of the screen
+    let show_sidebar = app.sidebar_visible || focused == View::Contacts;
+    let sidebar_width = if show_sidebar { app.config.sidebar_width.min(size.width / 2) } else { 0 };
     let panels = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Max(20),
-            Constraint::Length(9)
+            Constraint::Length(sidebar_width),
+            Constraint::Min(1)
         ].as_ref()).split(size);
 
     let sidebar = panels[0];
 
+    // The input box grows with its content
+    let input_width = panels[1].width.saturating_sub(2) as usize;
+    let (input_rows, (cursor_row, cursor_column)) = app.input.layout(input_width);
+    let input_height = input_rows.len().min(MAX_INPUT_HEIGHT).max(2);
+
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(1),
-            Constraint::Length(4)
+            Constraint::Length(input_height as u16 + 2)
         ].as_ref())
         .split(panels[1]);
 
+    app.sidebar_area = if show_sidebar { Some(sidebar) } else { None };
+    app.scroll_sidebar(sidebar.height.saturating_sub(2) as usize);
+    app.chat_area = chunks[0];
+    app.input_area = chunks[1];
+
+    draw_status_bar(f, app, screen[1]);
+
     // Contacts
-    if let Some(contact) = app.get_selected_contact() {
-        List::new(app.contacts.iter().map(|i| {
-            if i.number.clone() == contact.number.clone() {
-                return Text::styled(i.name.clone().unwrap(), Style::default().fg(Color::Blue));
+    if show_sidebar {
+        draw_sidebar(f, app, sidebar);
+    }
+
+    // Chat
+    chat::draw_chat(f, app, chunks[0]);
+    if !app.completions.is_empty() {
+        draw_completions(f, app, chunks[0]);
+    }
+    if let Some(selected) = app.reaction_picker {
+        draw_reaction_picker(f, &app.config.reactions, selected, chunks[0]);
+    }
+
+    // Input, scrolled to keep the cursor visible
+    let first_row = (cursor_row + 1).saturating_sub(input_height);
+    let input_text = input_rows[first_row..].join("\n");
+    let title = match &app.reply_to {
+        Some(quote) => {
+            let author = match app.get_contact(&quote.author) {
+                _ if quote.author == app.username => "Me".to_string(),
+                Some(c) => c.display_name(),
+                None => quote.author.clone(),
+            };
+            let text: String = quote.text.lines().next().unwrap_or("").chars().take(input_width / 2).collect();
+            format!("Replying to {}: {}", author, text)
+        }
+        None => String::new(),
+    };
+    Paragraph::new([Text::raw(input_text)].iter())
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style(focused == View::Chat))
+            .title(&title)
+        )
+        .render(f, chunks[1]);
+
+    app.draw_cursor = focused == View::Chat;
+    app.cursor_pos = Point {
+        x: chunks[1].x + cursor_column as u16 + 1,
+        y: chunks[1].y + (cursor_row - first_row) as u16 + 1,
+    }
+}
+
+/// The line at the bottom of the screen: mode, account, connection and the latest notice
+fn draw_status_bar<B>(f: &mut Frame<B>, app: &App, area: Rect)
+    where B: Backend,
+{
+    let mode = match app.focused_view {
+        View::Accounts => " ACCOUNTS ",
+        View::Contacts => " CONTACTS ",
+        View::Chat => " INPUT ",
+        View::Messages => " SELECT ",
+        View::Errors => " ERRORS ",
+    };
+    let account = if app.loaded {
+        app.username.clone()
+    } else {
+        "No account loaded".to_string()
+    };
+
+    let connection = match app.connection {
+        ConnectionState::Connected => Text::styled("Connected", Style::default().fg(Color::Green)),
+        ConnectionState::Connecting => Text::styled("Connecting…", Style::default().fg(Color::Yellow)),
+        ConnectionState::Disconnected { retry_at } => {
+            let seconds = retry_at.saturating_duration_since(Instant::now()).as_secs() + 1;
+            Text::styled(
+                format!("Disconnected, reconnecting in {}s", seconds),
+                Style::default().fg(Color::Red),
+            )
+        }
+    };
+
+    let mut text = vec![
+        Text::styled(mode, Style::default().modifier(Modifier::REVERSED)),
+        Text::raw(format!(" {} · ", account)),
+        connection,
+    ];
+    if !app.error_log.is_empty() {
+        text.push(Text::styled(format!(" · {} errors", app.error_log.len()), Style::default().fg(Color::Red)));
+    }
+    if let Some(notice) = &app.notice {
+        let color = match notice.kind {
+            NoticeKind::Info => Color::Reset,
+            NoticeKind::Error => Color::Red,
+        };
+        text.push(Text::raw(" │ "));
+        text.push(Text::styled(notice.text.clone(), Style::default().fg(color)));
+    }
+
+    Paragraph::new(text.iter())
+        .render(f, area);
+}
+
+/// The conversation list
+fn draw_sidebar<B>(f: &mut Frame<B>, app: &App, sidebar: Rect)
+    where B: Backend,
+{
+    let focused = app.focused_view;
+    if let Some(selected) = app.get_selected_conversation_id() {
+        let conversations = &app.conversations;
+        List::new(app.conversation_list.iter().skip(app.sidebar_scroll).filter_map(|id| {
+            let conv = conversations.get(id)?;
+            let mut style = Style::default();
+            if *id == selected {
+                style = style.fg(Color::Blue);
+            } else if app.muted.contains(id) {
+                style = style.fg(Color::DarkGray);
+            }
+            let mut title = conv.title();
+            if !conv.typing_members().is_empty() {
+                title.push_str(" ✎");
             }
-            return Text::styled(i.name.clone().unwrap(), Style::default());
+            if conv.unread > 0 {
+                return Some(Text::styled(
+                    format!("{} ({})", title, conv.unread),
+                    style.modifier(Modifier::BOLD),
+                ));
+            }
+            Some(Text::styled(title, style))
         }))
             .block(Block::default()
                 .borders(Borders::ALL)
+                .border_style(border_style(focused == View::Contacts))
                 .title("List")
             )
             .render(f, sidebar);
@@ -49,40 +204,130 @@ pub fn draw_basic_view<B>(f: &mut Frame<B>, app: &mut App)
             )
             .render(f, sidebar);
     }
+}
+
+/// The attachment picker, drawn over the bottom of the chat pane
+fn draw_completions<B>(f: &mut Frame<B>, app: &App, chat: Rect)
+    where B: Backend,
+{
+    let shown = app.completions.len().min(MAX_COMPLETIONS);
+    let height = (shown as u16 + 2).min(chat.height);
+    let area = Rect::new(chat.x, chat.y + chat.height - height, chat.width, height);
+    let width = area.width.saturating_sub(2) as usize;
+
+    // Keep the selected candidate in view
+    let selected = app.completion_index.unwrap_or(0);
+    let first = (selected + 1).saturating_sub(shown);
 
-// Chat
-    if let Some(conv) = app.get_current_conversation() {
-        List::new(conv.messages.iter_mut()
-            .map(|i| {
-                let date = Local.timestamp(i.timestamp / 1000, 0);
-                Text::raw(
-                    format!("{}: {}", date, i.message.clone())
-                )
-            })
+    // Pad the items so they cover the chat below
+    List::new(app.completions.iter().enumerate().skip(first).take(shown).map(|(i, c)| {
+        let text = format!("{:width$}", c, width = width);
+        if Some(i) == app.completion_index {
+            Text::styled(text, Style::default().fg(Color::Blue))
+        } else {
+            Text::raw(text)
+        }
+    }))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(&format!("Attach ({})", app.completions.len()))
         )
-            .block(Block::default()
-                .borders(Borders::ALL)
-                .title("Chat")
-            )
-            .render(f, chunks[0]);
+        .render(f, area);
+}
+
+/// Where the reaction picker for `count` emoji is drawn, at the bottom left of the chat
+pub fn reaction_picker_area(count: usize, chat: Rect) -> Rect {
+    // Emoji are two columns wide, with a space on both sides.
+    // The picker starts after the chat border, so it is one column narrower than the chat at most.
+    let width = (count as u16 * 4 + 2).min(chat.width.saturating_sub(1));
+    let height = 3.min(chat.height);
+    Rect::new(chat.x + 1, chat.y + chat.height - height, width, height)
+}
+
+/// The emoji to react with, drawn over the bottom of the chat pane
+fn draw_reaction_picker<B>(f: &mut Frame<B>, emoji: &[String], selected: usize, chat: Rect)
+    where B: Backend,
+{
+    let area = reaction_picker_area(emoji.len(), chat);
+
+    let text: Vec<Text> = emoji.iter().enumerate().map(|(i, e)| {
+        let text = format!(" {} ", e);
+        if i == selected {
+            Text::styled(text, Style::default().modifier(Modifier::REVERSED))
+        } else {
+            Text::raw(text)
+        }
+    }).collect();
+
+    Paragraph::new(text.iter())
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("React")
+        )
+        .render(f, area);
+}
+
+/// Highlight the border of the focused pane
+fn border_style(focused: bool) -> Style {
+    if focused {
+        Style::default().fg(Color::Yellow)
     } else {
-        Paragraph::new([Text::raw("No conversation selected")].iter())
+        Style::default()
+    }
+}
+
+pub fn draw_account_picker<B>(f: &mut Frame<B>, app: &mut App)
+    where B: Backend,
+{
+    let size = f.size();
+    app.draw_cursor = false;
+
+    if app.accounts.is_empty() {
+        Paragraph::new([Text::raw("No signald account found, link or register one with signald first")].iter())
             .block(Block::default()
                 .borders(Borders::ALL)
+                .title("Accounts")
             )
-            .render(f, chunks[0]);
+            .render(f, size);
+        return;
     }
 
-    // Input
-    Paragraph::new([Text::raw(&app.input_string)].iter())
+    List::new(app.accounts.iter().enumerate().map(|(i, a)| {
+        if i == app.selected_account_index {
+            return Text::styled(a.clone(), Style::default().fg(Color::Blue));
+        }
+        Text::raw(a.clone())
+    }))
         .block(Block::default()
             .borders(Borders::ALL)
+            .title("Select an account")
         )
-        .render(f, chunks[1]);
+        .render(f, size);
+}
 
-    app.draw_cursor = true;
-    app.cursor_pos = Point {
-        x: chunks[1].x + app.input_position as u16 + 1,
-        y: chunks[1].y + 1,
+/// The errors reported in this session, newest first
+pub fn draw_error_log<B>(f: &mut Frame<B>, app: &mut App)
+    where B: Backend,
+{
+    let size = f.size();
+    app.draw_cursor = false;
+
+    if app.error_log.is_empty() {
+        Paragraph::new([Text::raw("No errors")].iter())
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .title("Errors")
+            )
+            .render(f, size);
+        return;
     }
-}
\ No newline at end of file
+
+    List::new(app.error_log.iter().rev().map(|e| {
+        Text::raw(format!("{} {}", e.time.format("%H:%M:%S"), e.message))
+    }))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Errors")
+        )
+        .render(f, size);
+}
@@ -0,0 +1,228 @@
+use crate::app::{App, ConnectionState, Contact, DeliveryStatus, Group, Message, Reaction, View};
+use crate::error::Error;
+use crate::network::IoEvent;
+use crate::notify::Notification;
+use crate::ui::image::Preview;
+
+/// A change to the app state coming from signald.
+///
+/// The network only describes what happened, `App::apply` decides how the state changes,
+/// so state transitions can be tested without a socket or a terminal.
+#[derive(Debug)]
+pub enum StateUpdate {
+    ConnectionChanged(ConnectionState),
+    /// Several accounts are registered and none is configured, the user picks one
+    AccountsLoaded(Vec<String>),
+    AccountLoaded(String),
+    ContactsLoaded(Vec<Contact>),
+    GroupsLoaded(Vec<Group>),
+    /// Group info carried by a message, `None` fields are unchanged
+    GroupInfoReceived {
+        id: String,
+        name: Option<String>,
+        members: Option<Vec<String>>,
+    },
+    /// A message from someone else, or sent from another device
+    MessageReceived {
+        conversation: String,
+        message: Message,
+    },
+    /// A message of the user, shown before signald confirms it was sent
+    MessageQueued {
+        conversation: String,
+        message: Message,
+    },
+    MessageStatusChanged {
+        timestamp: i64,
+        status: DeliveryStatus,
+    },
+    ReactionReceived {
+        conversation: String,
+        reaction: Reaction,
+    },
+    TypingChanged {
+        conversation: String,
+        sender: String,
+        started: bool,
+    },
+    /// An image attachment was decoded, `None` when it isn't an image after all
+    PreviewLoaded {
+        path: String,
+        preview: Option<Preview>,
+    },
+    /// Sending `what`, e.g. "message", to a conversation failed
+    SendFailed {
+        recipient: String,
+        what: &'static str,
+    },
+    Failed(Error),
+}
+
+/// Work that follows from a state update, done by the caller of `App::apply`.
+/// Applying an update only changes the state, so it never waits on the disk or the network.
+#[derive(Debug)]
+pub enum Effect {
+    /// Open the message history of the loaded account
+    OpenStore,
+    /// Load the history of a conversation that was just added
+    LoadHistory(String),
+    /// Reorder the sidebar, loaded histories tell which conversations were active last
+    SortConversations,
+    /// Select the most recently active conversation, once the first conversations are loaded
+    SelectNewest,
+    SaveMessage {
+        conversation: String,
+        message: Message,
+    },
+    SaveReaction {
+        conversation: String,
+        reaction: Reaction,
+    },
+    /// Save the new status of an own message
+    SaveStatus {
+        conversation: String,
+        timestamp: i64,
+        status: DeliveryStatus,
+    },
+    /// Save the drafts after the input moved to another conversation
+    SaveDrafts,
+    /// Pass an event to the network
+    Send(IoEvent),
+    /// Notify about an incoming message
+    Notify(Notification),
+}
+
+impl App {
+    /// Apply a state update, returns the effects the caller has to `perform`
+    pub fn apply(&mut self, update: StateUpdate) -> Vec<Effect> {
+        let first_conversations = self.conversation_list.is_empty();
+        let mut effects = match update {
+            StateUpdate::ConnectionChanged(connection) => {
+                self.connection = connection;
+                Vec::new()
+            }
+            StateUpdate::AccountsLoaded(accounts) => {
+                self.pick_account(accounts);
+                Vec::new()
+            }
+            // Loading the same account again, e.g. after reconnecting, keeps the view and the history
+            StateUpdate::AccountLoaded(username) if self.loaded && username == self.username => {
+                Vec::new()
+            }
+            StateUpdate::AccountLoaded(username) => {
+                self.username = username;
+                self.loaded = true;
+                self.focused_view = View::Contacts;
+                vec![Effect::OpenStore]
+            }
+            StateUpdate::ContactsLoaded(contacts) => {
+                self.update_contacts(contacts)
+            }
+            StateUpdate::GroupsLoaded(groups) => {
+                self.update_groups(groups)
+            }
+            StateUpdate::GroupInfoReceived { id, name, members } => {
+                // Create unknown groups and apply name and member updates
+                let known = self.groups.iter().find(|g| g.id == id).cloned();
+                if known.is_none() || name.is_some() || members.is_some() {
+                    let known = known.unwrap_or(Group {
+                        id: id.clone(),
+                        name: None,
+                        members: Vec::new(),
+                    });
+                    self.update_group(Group {
+                        id,
+                        name: name.or(known.name),
+                        members: members.unwrap_or(known.members),
+                    })
+                } else {
+                    Vec::new()
+                }
+            }
+            StateUpdate::MessageReceived { conversation, message } => {
+                let notification = self.notification_for(&conversation, &message);
+                let mut effects = self.add_message(conversation, message);
+                effects.extend(notification.map(Effect::Notify));
+                effects
+            }
+            StateUpdate::MessageQueued { conversation, message } => {
+                self.add_message(conversation, message)
+            }
+            StateUpdate::MessageStatusChanged { timestamp, status } => {
+                self.update_message_status(timestamp, status).into_iter().collect()
+            }
+            StateUpdate::ReactionReceived { conversation, reaction } => {
+                vec![self.add_reaction(conversation, reaction)]
+            }
+            StateUpdate::TypingChanged { conversation, sender, started } => {
+                self.set_typing(&conversation, &sender, started);
+                Vec::new()
+            }
+            StateUpdate::PreviewLoaded { path, preview } => {
+                self.pending_previews.remove(&path);
+                self.image_previews.insert(path, preview);
+                Vec::new()
+            }
+            StateUpdate::SendFailed { recipient, what } => {
+                let title = self.conversation_title(&recipient);
+                self.report(Error::Request(format!("send {} to {}", what, title)));
+                Vec::new()
+            }
+            StateUpdate::Failed(error) => {
+                self.report(error);
+                Vec::new()
+            }
+        };
+
+        // The sidebar is ordered by the newest message, which is only known once the history is loaded
+        if effects.iter().any(|e| matches!(e, Effect::LoadHistory(_))) {
+            effects.push(Effect::SortConversations);
+            if first_conversations {
+                effects.push(Effect::SelectNewest);
+            }
+        }
+        effects
+    }
+
+    /// Do the effects of state updates, reporting what fails
+    pub fn perform(&mut self, effects: Vec<Effect>) {
+        for effect in effects {
+            match effect {
+                Effect::OpenStore => {
+                    self.open_store();
+                }
+                Effect::LoadHistory(id) => {
+                    self.load_history(id);
+                }
+                Effect::SortConversations => {
+                    let effects = self.update_conversation_list();
+                    self.perform(effects);
+                }
+                Effect::SelectNewest => {
+                    self.select_conversation(0);
+                }
+                Effect::SaveMessage { conversation, message } => {
+                    self.with_store("save a message", |s| s.append(&conversation, &message));
+                }
+                Effect::SaveReaction { conversation, reaction } => {
+                    self.with_store("save a reaction", |s| s.append_reaction(&conversation, &reaction));
+                }
+                Effect::SaveStatus { conversation, timestamp, status } => {
+                    let username = self.username.clone();
+                    self.with_store("save the status of a message", |s| {
+                        s.append_status(&conversation, &username, timestamp, status)
+                    });
+                }
+                Effect::SaveDrafts => {
+                    self.save_drafts();
+                }
+                Effect::Send(event) => {
+                    self.send_io(event);
+                }
+                Effect::Notify(notification) => {
+                    self.notify(&notification);
+                }
+            }
+        }
+    }
+}
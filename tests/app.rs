@@ -0,0 +1,359 @@
+use std::time::Instant;
+
+use signald_tui::app::{App, ChatFrame, Contact, Message, NoticeKind, Reaction, View};
+use signald_tui::config::Config;
+use signald_tui::error::Error;
+use signald_tui::network::IoEvent;
+use signald_tui::update::{Effect, StateUpdate};
+
+use common::{app, app_with_store, message, TempPath, CONTACT, OTHER_CONTACT, USERNAME};
+
+mod common;
+
+fn type_text(app: &mut App, text: &str) {
+    for c in text.chars() {
+        app.input.insert(c);
+    }
+}
+
+fn send(app: &mut App, text: &str) {
+    let effects = app.add_message(CONTACT.to_string(), Message {
+        message: text.to_string(),
+        ..message(USERNAME, 0)
+    });
+    app.perform(effects);
+}
+
+fn receive(app: &mut App, timestamp: i64) {
+    let effects = app.add_message(CONTACT.to_string(), message(CONTACT, timestamp));
+    app.perform(effects);
+}
+
+#[test]
+fn scrolling_is_clamped_to_loaded_messages() {
+    let (mut app, _rx) = app();
+    app.chat_frame = ChatFrame { shown: 10, max_scroll: 20 };
+    for i in 0..30 {
+        receive(&mut app, i);
+    }
+
+    app.scroll_up(9);
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 9);
+
+    app.scroll_to_top();
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 20);
+
+    app.scroll_down(100);
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 0);
+}
+
+#[test]
+fn pages_keep_one_message_in_view() {
+    let (mut app, _rx) = app();
+    for i in 0..30 {
+        receive(&mut app, i);
+    }
+    // Messages of three rows in a pane of twenty
+    app.chat_frame = ChatFrame { shown: 7, max_scroll: 24 };
+
+    app.page_up();
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 6);
+
+    app.page_down();
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 0);
+}
+
+#[test]
+fn new_messages_are_counted_while_scrolled_up() {
+    let (mut app, _rx) = app();
+    app.chat_frame = ChatFrame { shown: 10, max_scroll: 20 };
+    for i in 0..30 {
+        receive(&mut app, i);
+    }
+
+    app.scroll_up(5);
+    receive(&mut app, 30);
+    receive(&mut app, 31);
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.scroll, 7);
+    assert_eq!(conv.new_below, 2);
+
+    app.scroll_down(6);
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.scroll, 1);
+    assert_eq!(conv.new_below, 1);
+
+    app.scroll_to_bottom();
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.scroll, 0);
+    assert_eq!(conv.new_below, 0);
+}
+
+#[test]
+fn following_conversation_stays_at_bottom() {
+    let (mut app, _rx) = app();
+    app.chat_frame = ChatFrame { shown: 10, max_scroll: 20 };
+    for i in 0..30 {
+        receive(&mut app, i);
+    }
+
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.scroll, 0);
+    assert_eq!(conv.new_below, 0);
+}
+
+#[test]
+fn drafts_follow_the_selected_conversation() {
+    let data_dir = TempPath::new("signald-tui-data");
+    let (mut app, _rx) = app_with_store(&data_dir);
+    assert_eq!(app.get_selected_conversation_id(), Some(CONTACT.to_string()));
+
+    type_text(&mut app, "for alice");
+    app.select_conversation(1);
+    assert!(app.input.is_empty());
+
+    type_text(&mut app, "for bob");
+    app.select_conversation(0);
+    assert_eq!(app.input.text(), "for alice");
+
+    app.select_conversation(1);
+    assert_eq!(app.input.text(), "for bob");
+}
+
+#[test]
+fn drafts_are_restored_after_restart() {
+    let data_dir = TempPath::new("signald-tui-data");
+    {
+        let (mut app, _rx) = app_with_store(&data_dir);
+        type_text(&mut app, "for alice");
+        app.select_conversation(1);
+        type_text(&mut app, "for bob");
+        app.save_drafts();
+    }
+
+    let (mut app, _rx) = app_with_store(&data_dir);
+    assert_eq!(app.input.text(), "for alice");
+    app.select_conversation(1);
+    assert_eq!(app.input.text(), "for bob");
+}
+
+#[test]
+fn drafts_are_saved_when_switching_conversations() {
+    let data_dir = TempPath::new("signald-tui-data");
+    {
+        let (mut app, _rx) = app_with_store(&data_dir);
+        type_text(&mut app, "for alice");
+        app.select_conversation(1);
+    }
+
+    let (app, _rx) = app_with_store(&data_dir);
+    assert_eq!(app.input.text(), "for alice");
+}
+
+#[test]
+fn newest_conversation_is_selected_on_load() {
+    let data_dir = TempPath::new("signald-tui-data");
+    {
+        let (mut app, _rx) = app_with_store(&data_dir);
+        let effects = app.apply(StateUpdate::MessageReceived {
+            conversation: OTHER_CONTACT.to_string(),
+            message: Message { sender: OTHER_CONTACT.to_string(), ..message(CONTACT, 1000) },
+        });
+        app.perform(effects);
+    }
+
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::with_config(tx, Config {
+        data_dir: Some(data_dir.to_path_buf()),
+        ..Config::default()
+    });
+    let effects = app.apply(StateUpdate::AccountLoaded(USERNAME.to_string()));
+    app.perform(effects);
+    let effects = app.apply(StateUpdate::ContactsLoaded(vec![
+        Contact { number: CONTACT.to_string(), name: Some("Alice".to_string()), color: None },
+        Contact { number: OTHER_CONTACT.to_string(), name: Some("Bob".to_string()), color: None },
+    ]));
+    assert!(matches!(effects.last(), Some(Effect::SelectNewest)));
+    app.perform(effects);
+    assert_eq!(app.get_selected_conversation_id(), Some(OTHER_CONTACT.to_string()));
+
+    // Later lists keep the selection
+    let effects = app.apply(StateUpdate::GroupsLoaded(Vec::new()));
+    assert!(!effects.iter().any(|e| matches!(e, Effect::SelectNewest)));
+}
+
+#[test]
+fn sent_messages_are_recalled() {
+    let (mut app, _rx) = app();
+    send(&mut app, "first");
+    send(&mut app, "second");
+    type_text(&mut app, "unfinished");
+
+    app.recall_previous_message();
+    assert_eq!(app.input.text(), "second");
+    app.recall_previous_message();
+    assert_eq!(app.input.text(), "first");
+    // The oldest message stays
+    app.recall_previous_message();
+    assert_eq!(app.input.text(), "first");
+
+    app.recall_next_message();
+    assert_eq!(app.input.text(), "second");
+    app.recall_next_message();
+    assert_eq!(app.input.text(), "unfinished");
+    app.recall_next_message();
+    assert_eq!(app.input.text(), "unfinished");
+}
+
+fn reaction(sender: &str, emoji: &str, target_timestamp: i64) -> Reaction {
+    Reaction {
+        sender: sender.to_string(),
+        emoji: emoji.to_string(),
+        target_author: CONTACT.to_string(),
+        target_timestamp,
+        remove: false,
+    }
+}
+
+#[test]
+fn reactions_are_counted_per_message() {
+    let (mut app, _rx) = app();
+    receive(&mut app, 1);
+    receive(&mut app, 2);
+
+    app.add_reaction(CONTACT.to_string(), reaction(USERNAME, "👍", 1));
+    app.add_reaction(CONTACT.to_string(), reaction(CONTACT, "👍", 1));
+    app.add_reaction(CONTACT.to_string(), reaction(OTHER_CONTACT, "😂", 1));
+    // A new reaction replaces the earlier one of the same sender
+    app.add_reaction(CONTACT.to_string(), reaction(OTHER_CONTACT, "❤️", 1));
+
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.reaction_counts(&conv.messages[0]), vec![("👍".to_string(), 2), ("❤️".to_string(), 1)]);
+    assert!(conv.reaction_counts(&conv.messages[1]).is_empty());
+
+    app.add_reaction(CONTACT.to_string(), Reaction { remove: true, ..reaction(USERNAME, "👍", 1) });
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.reaction_counts(&conv.messages[0]), vec![("👍".to_string(), 1), ("❤️".to_string(), 1)]);
+}
+
+#[test]
+fn reactions_are_restored_after_restart() {
+    let dir = TempPath::new("signald-tui-data");
+    {
+        let (mut app, _rx) = app_with_store(&dir);
+        receive(&mut app, 1);
+        let effect = app.add_reaction(CONTACT.to_string(), reaction(USERNAME, "👍", 1));
+        app.perform(vec![effect]);
+    }
+
+    let (mut app, _rx) = app_with_store(&dir);
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.reaction_counts(&conv.messages[0]), vec![("👍".to_string(), 1)]);
+}
+
+#[test]
+fn message_selection_keeps_selected_message_at_bottom() {
+    let (mut app, _rx) = app();
+    app.focused_view = View::Chat;
+    for i in 0..5 {
+        receive(&mut app, i);
+    }
+
+    app.select_messages();
+    assert_eq!(app.focused_view, View::Messages);
+    assert_eq!(app.selected_message, Some(4));
+
+    app.move_message_selection(-2);
+    assert_eq!(app.selected_message, Some(2));
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 2);
+
+    app.move_message_selection(-5);
+    assert_eq!(app.selected_message, Some(0));
+    app.move_message_selection(10);
+    assert_eq!(app.selected_message, Some(4));
+    assert_eq!(app.get_selected_message().unwrap().timestamp, 4);
+}
+
+#[test]
+fn notices_expire() {
+    let (mut app, _rx) = app();
+    app.show_error("Could not send".to_string());
+    app.expire_notice();
+    assert_eq!(app.notice.as_ref().unwrap().kind, NoticeKind::Error);
+
+    app.notice.as_mut().unwrap().expires = Instant::now();
+    app.expire_notice();
+    assert!(app.notice.is_none());
+}
+
+#[test]
+fn saving_without_attachment_shows_error() {
+    let (mut app, _rx) = app();
+    app.save_selected_attachment();
+    assert_eq!(app.notice.unwrap().text, "No attachment in view");
+}
+
+#[test]
+fn previews_are_requested_once() {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::new(tx);
+    let path = "/tmp/photo.png".to_string();
+
+    app.request_previews(vec![path.clone()]);
+    app.request_previews(vec![path.clone()]);
+    assert!(matches!(rx.try_recv(), Ok(IoEvent::LoadPreview(_))));
+    assert!(rx.try_recv().is_err());
+    assert!(app.pending_previews.contains(&path));
+}
+
+#[test]
+fn reported_errors_are_logged() {
+    let (mut app, _rx) = app();
+    app.report(Error::Request("send message to Alice".to_string()));
+
+    assert_eq!(app.error_log.len(), 1);
+    assert_eq!(app.error_log[0].message, "Could not send message to Alice");
+    let notice = app.notice.unwrap();
+    assert_eq!(notice.kind, NoticeKind::Error);
+    assert_eq!(notice.text, "Could not send message to Alice");
+}
+
+#[test]
+fn store_failures_are_reported() {
+    // A file where the data directory should be
+    let data_dir = TempPath::new("signald-tui-data");
+    std::fs::write(&data_dir, b"").unwrap();
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::with_config(tx, Config {
+        data_dir: Some(data_dir.to_path_buf()),
+        ..Config::default()
+    });
+    app.username = USERNAME.to_string();
+
+    app.open_store();
+    assert!(app.store.is_none());
+    assert_eq!(app.error_log.len(), 1);
+    assert!(app.error_log[0].message.starts_with("Could not open the message history"));
+}
+
+#[test]
+fn closed_network_is_reported() {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::new(tx);
+    drop(rx);
+
+    app.send_io(IoEvent::LoadAccount);
+    assert_eq!(app.error_log.len(), 1);
+    assert_eq!(app.error_log[0].message, Error::NetworkClosed.to_string());
+}
+
+#[test]
+fn error_log_returns_to_previous_view() {
+    let (mut app, _rx) = app();
+    app.focused_view = View::Chat;
+
+    app.toggle_error_log();
+    assert_eq!(app.focused_view, View::Errors);
+    app.toggle_error_log();
+    assert_eq!(app.focused_view, View::Chat);
+}
@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+
+use signald_tui::attachment::{human_size, save, Attachment};
+
+use common::TempPath;
+
+mod common;
+
+fn received(path: &Path) -> Attachment {
+    Attachment {
+        filename: "cat.jpg".to_string(),
+        content_type: Some("image/jpeg".to_string()),
+        size: Some(4),
+        path: Some(path.display().to_string()),
+    }
+}
+
+#[test]
+fn saved_attachments_are_not_overwritten() {
+    let stored = TempPath::new("signald-tui-stored");
+    fs::write(&stored, b"meow").unwrap();
+    let dir = TempPath::new("signald-tui-downloads");
+
+    let first = save(&received(&stored), &dir).unwrap();
+    let second = save(&received(&stored), &dir).unwrap();
+    assert_eq!(first, dir.join("cat.jpg"));
+    assert_eq!(second, dir.join("cat (1).jpg"));
+    assert_eq!(fs::read(second).unwrap(), b"meow");
+}
+
+#[test]
+fn attachments_without_a_file_are_not_saved() {
+    let attachment = Attachment { path: None, ..received(Path::new("/unused/cat.jpg")) };
+    assert!(save(&attachment, &TempPath::new("signald-tui-downloads")).is_err());
+}
+
+#[test]
+fn summary_shows_type_and_size() {
+    assert_eq!(received(Path::new("/unused/cat.jpg")).summary(), "cat.jpg (image/jpeg, 4 B)");
+}
+
+#[test]
+fn sizes_are_human_readable() {
+    assert_eq!(human_size(512), "512 B");
+    assert_eq!(human_size(1_500), "1.5 KB");
+    assert_eq!(human_size(2_300_000), "2.3 MB");
+}
@@ -0,0 +1,558 @@
+#![allow(dead_code)]
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::ops::Deref;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use signald_tui::app::{App, Contact, DeliveryStatus, Message};
+use signald_tui::config::Config;
+use signald_tui::network::{IoEvent, Network};
+use signald_tui::notify::{Notification, Notifier};
+use signald_tui::update::StateUpdate;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// The account of the app fixtures
+pub const USERNAME: &str = "+32400000000";
+/// Alice, the first conversation of the app fixtures
+pub const CONTACT: &str = "+32411111111";
+/// Bob, the second conversation of the app fixtures
+pub const OTHER_CONTACT: &str = "+32422222222";
+
+/// A notifier that always fails, like a desktop notification without a notification daemon
+pub struct FailingNotifier;
+
+impl Notifier for FailingNotifier {
+    fn notify(&mut self, _notification: &Notification) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "No notification daemon"))
+    }
+}
+
+/// A stand-in for the signald daemon.
+///
+/// Listens on a unix socket and speaks signald's newline delimited json protocol. Responses to
+/// `list_accounts`, `list_contacts` and `subscribe` are served from the scripted state, and every
+/// request the client sends is recorded so tests can assert on it.
+pub struct MockSignald {
+    dir: TempPath,
+    socket_path: PathBuf,
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    accounts: Vec<Value>,
+    contacts: Vec<Value>,
+    groups: Vec<Value>,
+    incoming: Vec<Value>,
+    requests: Vec<Value>,
+    sent_timestamps: Vec<i64>,
+    clock: i64,
+    clients: Vec<UnixStream>,
+}
+
+impl MockSignald {
+    /// Create a mock in a unique directory in the temp directory, removed when the mock is dropped
+    pub fn new() -> Self {
+        let dir = TempPath::new("signald-tui-mock");
+        std::fs::create_dir_all(&dir).expect("Could not create mock directory");
+        Self {
+            socket_path: dir.join("signald.sock"),
+            dir,
+            state: Arc::new(Mutex::new(MockState::default())),
+        }
+    }
+
+    pub fn socket_path(&self) -> &str {
+        self.socket_path.to_str().unwrap()
+    }
+
+    /// A data directory for the client, removed along with the socket
+    pub fn data_dir(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    /// Register an account which is returned by `list_accounts`
+    pub fn with_account(self, username: &str) -> Self {
+        self.state.lock().unwrap().accounts.push(json!({
+            "username": username,
+            "deviceId": 1,
+            "filename": format!("/var/lib/signald/data/{}", username),
+            "registered": true,
+            "has_keys": true,
+            "subscribed": false,
+        }));
+        self
+    }
+
+    /// Register a contact which is returned by `list_contacts`
+    pub fn with_contact(self, number: &str, name: &str) -> Self {
+        self.state.lock().unwrap().contacts.push(json!({
+            "name": name,
+            "number": number,
+            "color": "blue",
+        }));
+        self
+    }
+
+    /// Register a group which is returned by `list_groups`
+    pub fn with_group(self, id: &str, name: &str, members: &[&str]) -> Self {
+        self.state.lock().unwrap().groups.push(json!({
+            "groupId": id,
+            "name": name,
+            "members": members,
+            "avatarId": 0,
+        }));
+        self
+    }
+
+    /// Queue a message which is delivered once a client subscribes
+    pub fn with_message(self, message: Value) -> Self {
+        self.state.lock().unwrap().incoming.push(message);
+        self
+    }
+
+    /// Start accepting connections on the socket path
+    pub fn start(self) -> Self {
+        let _ = std::fs::remove_file(&self.socket_path);
+        let listener = UnixListener::bind(&self.socket_path).expect("Could not bind mock socket");
+
+        let state = Arc::clone(&self.state);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => break,
+                };
+                let state = Arc::clone(&state);
+                thread::spawn(move || handle_client(stream, state));
+            }
+        });
+
+        self
+    }
+
+    /// Send a line to every connected client
+    pub fn push(&self, response: Value) {
+        let mut state = self.state.lock().unwrap();
+        for client in state.clients.iter_mut() {
+            let _ = write_line(client, &response);
+        }
+    }
+
+    /// Close every client connection, like signald does when it restarts.
+    /// New connections are still accepted.
+    pub fn disconnect(&self) {
+        let mut state = self.state.lock().unwrap();
+        for client in state.clients.drain(..) {
+            let _ = client.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The timestamps of the sent messages, which recipients refer to in receipts
+    pub fn sent_timestamps(&self) -> Vec<i64> {
+        self.state.lock().unwrap().sent_timestamps.clone()
+    }
+
+    /// All received requests of a given type
+    pub fn requests_of_type(&self, request_type: &str) -> Vec<Value> {
+        self.requests().into_iter()
+            .filter(|r| r["type"] == request_type)
+            .collect()
+    }
+
+    /// Build an incoming data message as signald would deliver it
+    pub fn data_message(username: &str, source: &str, timestamp: i64, body: &str) -> Value {
+        json!({
+            "type": "message",
+            "data": {
+                "username": username,
+                "source": source,
+                "sourceDevice": 1,
+                "type": 1,
+                "timestamp": timestamp,
+                "timestampISO": "",
+                "serverTimestamp": timestamp,
+                "hasLegacyMessage": false,
+                "hasContent": true,
+                "isUnidentifiedSender": false,
+                "dataMessage": {
+                    "timestamp": timestamp,
+                    "message": body,
+                    "expiresInSeconds": 0,
+                    "attachments": [],
+                },
+            },
+        })
+    }
+
+    /// Build an incoming data message with a file signald stored at `stored_path`
+    pub fn attachment_message(username: &str, source: &str, timestamp: i64, filename: &str, stored_path: &str) -> Value {
+        let mut message = MockSignald::data_message(username, source, timestamp, "");
+        message["data"]["dataMessage"]["attachments"] = json!([{
+            "contentType": "image/jpeg",
+            "id": "1234567890",
+            "size": 2048,
+            "storedFilename": stored_path,
+            "customFilename": filename,
+            "width": 640,
+            "height": 480,
+            "voiceNote": false,
+        }]);
+        message
+    }
+
+    /// Build an incoming reaction to the message of `target_author` sent at `target_timestamp`
+    pub fn reaction_message(username: &str, source: &str, timestamp: i64, emoji: &str, target_author: &str, target_timestamp: i64) -> Value {
+        let mut message = MockSignald::data_message(username, source, timestamp, "");
+        message["data"]["dataMessage"]["reaction"] = json!({
+            "emoji": emoji,
+            "remove": false,
+            "targetAuthor": { "number": target_author },
+            "targetSentTimestamp": target_timestamp,
+        });
+        message
+    }
+
+    /// Build an incoming reply quoting the message of `quote_author` sent at `quote_id`
+    pub fn reply_message(username: &str, source: &str, timestamp: i64, body: &str, quote_author: &str, quote_id: i64, quote_text: &str) -> Value {
+        let mut message = MockSignald::data_message(username, source, timestamp, body);
+        message["data"]["dataMessage"]["quote"] = json!({
+            "id": quote_id,
+            "author": { "number": quote_author },
+            "text": quote_text,
+            "attachments": [],
+        });
+        message
+    }
+
+    /// Build an incoming data message sent to a group
+    pub fn group_message(username: &str, source: &str, group_id: &str, timestamp: i64, body: &str) -> Value {
+        let mut message = MockSignald::data_message(username, source, timestamp, body);
+        message["data"]["dataMessage"]["groupInfo"] = json!({
+            "groupId": group_id,
+            "type": "DELIVER",
+        });
+        message
+    }
+
+    /// Build a delivery or read receipt, `receipt_type` is either "DELIVERY" or "READ"
+    pub fn receipt_message(username: &str, source: &str, receipt_type: &str, timestamps: &[i64]) -> Value {
+        json!({
+            "type": "message",
+            "data": {
+                "username": username,
+                "source": source,
+                "sourceDevice": 1,
+                "type": 5,
+                "timestamp": timestamps.iter().max().cloned().unwrap_or(0),
+                "timestampISO": "",
+                "serverTimestamp": 0,
+                "hasLegacyMessage": false,
+                "hasContent": true,
+                "isUnidentifiedSender": false,
+                "receipt": {
+                    "type": receipt_type,
+                    "timestamps": timestamps,
+                    "when": 0,
+                },
+            },
+        })
+    }
+
+    /// Build a typing indicator, `action` is either "STARTED" or "STOPPED"
+    pub fn typing_message(username: &str, source: &str, action: &str, group_id: Option<&str>) -> Value {
+        json!({
+            "type": "message",
+            "data": {
+                "username": username,
+                "source": source,
+                "sourceDevice": 1,
+                "type": 1,
+                "timestamp": 0,
+                "timestampISO": "",
+                "serverTimestamp": 0,
+                "hasLegacyMessage": false,
+                "hasContent": true,
+                "isUnidentifiedSender": false,
+                "typing": {
+                    "action": action,
+                    "timestamp": 0,
+                    "groupId": group_id,
+                },
+            },
+        })
+    }
+
+    /// Build a sync message for a message sent from another device of the account
+    pub fn sync_message(username: &str, destination: &str, timestamp: i64, body: &str) -> Value {
+        json!({
+            "type": "message",
+            "data": {
+                "username": username,
+                "source": username,
+                "sourceDevice": 2,
+                "type": 1,
+                "timestamp": timestamp,
+                "timestampISO": "",
+                "serverTimestamp": timestamp,
+                "hasLegacyMessage": false,
+                "hasContent": true,
+                "isUnidentifiedSender": false,
+                "syncMessage": {
+                    "sent": {
+                        "destination": destination,
+                        "timestamp": timestamp,
+                        "expirationStartTimestamp": 0,
+                        "isRecipientUpdate": false,
+                        "message": {
+                            "timestamp": timestamp,
+                            "message": body,
+                            "expiresInSeconds": 0,
+                            "attachments": [],
+                        },
+                    },
+                },
+            },
+        })
+    }
+}
+
+fn handle_client(stream: UnixStream, state: Arc<Mutex<MockState>>) {
+    let mut writer = stream.try_clone().unwrap();
+    state.lock().unwrap().clients.push(stream.try_clone().unwrap());
+
+    let _ = write_line(&mut writer, &json!({
+        "type": "version",
+        "data": {
+            "name": "signald",
+            "version": "mock",
+            "branch": "mock",
+            "commit": "mock",
+        },
+    }));
+
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        let responses = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(request.clone());
+            respond(&request, &mut state)
+        };
+
+        for response in responses {
+            if write_line(&mut writer, &response).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn respond(request: &Value, state: &mut MockState) -> Vec<Value> {
+    let id = request["id"].clone();
+    match request["type"].as_str() {
+        Some("list_accounts") => vec![json!({
+            "type": "account_list",
+            "id": id,
+            "data": { "accounts": state.accounts },
+        })],
+        Some("list_contacts") => vec![json!({
+            "type": "contact_list",
+            "id": id,
+            "data": state.contacts,
+        })],
+        Some("list_groups") => vec![json!({
+            "type": "group_list",
+            "id": id,
+            "data": { "groups": state.groups },
+        })],
+        Some("subscribe") => {
+            let mut responses = vec![json!({ "type": "subscribed", "id": id })];
+            responses.append(&mut state.incoming);
+            responses
+        }
+        Some("send") => {
+            // Like signald, stamp the message itself unless the request carries a timestamp
+            state.clock += 1;
+            let timestamp = request["timestamp"].as_i64().unwrap_or(state.clock);
+            state.sent_timestamps.push(timestamp);
+            vec![json!({ "type": "success", "id": id, "data": { "timestamp": timestamp } })]
+        }
+        Some("unsubscribe") => vec![json!({ "type": "unsubscribed", "id": id })],
+        _ => vec![],
+    }
+}
+
+fn write_line(stream: &mut UnixStream, value: &Value) -> io::Result<()> {
+    let mut line = value.to_string();
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()
+}
+
+/// A unique, not yet existing path in the temp directory.
+/// Whatever is created at the path is removed when it is dropped.
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    pub fn new(prefix: &str) -> Self {
+        TempPath(std::env::temp_dir().join(format!("{}-{}", prefix, rand::random::<u64>())))
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            let _ = std::fs::remove_dir_all(&self.0);
+        } else {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
+
+/// The app as the UI loop owns it, with the state updates a network sends it
+pub struct Ui {
+    pub app: App,
+    updates: UnboundedReceiver<StateUpdate>,
+}
+
+impl Ui {
+    pub fn new(app: App, updates: UnboundedReceiver<StateUpdate>) -> Self {
+        Self { app, updates }
+    }
+
+    /// Apply the updates received so far and perform their effects, like the UI loop does
+    pub fn sync(&mut self) -> &mut App {
+        while let Ok(update) = self.updates.try_recv() {
+            let effects = self.app.apply(update);
+            self.app.perform(effects);
+        }
+        &mut self.app
+    }
+
+    /// Apply updates as they arrive until `condition` holds for the app or the timeout passes
+    pub async fn wait_until<F>(&mut self, timeout: Duration, condition: F) -> bool
+        where F: Fn(&mut App) -> bool,
+    {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !condition(self.sync()) {
+            match tokio::time::timeout_at(deadline, self.updates.recv()).await {
+                Ok(Some(update)) => {
+                    let effects = self.app.apply(update);
+                    self.app.perform(effects);
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Tick the network until `condition` holds for the app or the timeout passes
+pub async fn tick_until<F>(network: &mut Network, ui: &mut Ui, timeout: Duration, condition: F) -> bool
+    where F: Fn(&mut App) -> bool,
+{
+    let step = Duration::from_millis(10);
+    let mut waited = Duration::from_millis(0);
+    while waited < timeout {
+        network.poll().await;
+        if condition(ui.sync()) {
+            return true;
+        }
+        tokio::time::delay_for(step).await;
+        waited += step;
+    }
+    false
+}
+
+/// Wait until the mock received a request of `request_type` or the timeout passes
+pub async fn wait_for_request(mock: &MockSignald, request_type: &str, timeout: Duration) -> bool {
+    let step = Duration::from_millis(10);
+    let mut waited = Duration::from_millis(0);
+    while waited < timeout {
+        if !mock.requests_of_type(request_type).is_empty() {
+            return true;
+        }
+        tokio::time::delay_for(step).await;
+        waited += step;
+    }
+    false
+}
+
+/// An app logged in as `USERNAME` with conversations with Alice and Bob, and the receiving end of its io channel.
+/// The effects of loading are dropped, so nothing touches the disk.
+pub fn app() -> (App, UnboundedReceiver<IoEvent>) {
+    let (tx, rx) = mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::new(tx);
+    app.apply(StateUpdate::AccountLoaded(USERNAME.to_string()));
+    app.apply(StateUpdate::ContactsLoaded(contacts()));
+    (app, rx)
+}
+
+/// Like `app`, keeping the message history in `data_dir`
+pub fn app_with_store(data_dir: &Path) -> (App, UnboundedReceiver<IoEvent>) {
+    let (tx, rx) = mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::with_config(tx, Config {
+        data_dir: Some(data_dir.to_path_buf()),
+        ..Config::default()
+    });
+    for update in vec![StateUpdate::AccountLoaded(USERNAME.to_string()), StateUpdate::ContactsLoaded(contacts())] {
+        let effects = app.apply(update);
+        app.perform(effects);
+    }
+    (app, rx)
+}
+
+fn contacts() -> Vec<Contact> {
+    vec![
+        Contact { number: CONTACT.to_string(), name: Some("Alice".to_string()), color: None },
+        Contact { number: OTHER_CONTACT.to_string(), name: Some("Bob".to_string()), color: None },
+    ]
+}
+
+/// A message between `USERNAME` and Alice with the text "message <timestamp>"
+pub fn message(sender: &str, timestamp: i64) -> Message {
+    let own = sender == USERNAME;
+    Message {
+        sender: sender.to_string(),
+        receiver: if own { CONTACT } else { USERNAME }.to_string(),
+        timestamp,
+        message: format!("message {}", timestamp),
+        status: if own { DeliveryStatus::Sent } else { DeliveryStatus::Received },
+        quote: None,
+        attachments: Vec::new(),
+    }
+}
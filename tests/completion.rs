@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::PathBuf;
+
+use signald_tui::completion::{attach_path, common_prefix, complete_path};
+
+use common::TempPath;
+
+mod common;
+
+/// A directory with `notes.txt`, `notes.md`, `photo.png`, `.hidden` and `pictures/`,
+/// along with its path ending in a slash
+fn fixture() -> (TempPath, String) {
+    let dir = TempPath::new("signald-tui-completion");
+    fs::create_dir_all(dir.join("pictures")).unwrap();
+    for name in ["notes.txt", "notes.md", "photo.png", ".hidden"].iter() {
+        fs::write(dir.join(name), b"").unwrap();
+    }
+    let path = format!("{}/", dir.display());
+    (dir, path)
+}
+
+#[test]
+fn paths_are_completed_from_the_directory() {
+    let (_guard, dir) = fixture();
+    let candidates = complete_path(&format!("{}p", dir));
+    assert_eq!(candidates, vec![format!("{}photo.png", dir), format!("{}pictures/", dir)]);
+}
+
+#[test]
+fn hidden_files_need_a_dot() {
+    let (_guard, dir) = fixture();
+    assert_eq!(complete_path(&dir).len(), 4);
+    assert_eq!(complete_path(&format!("{}.", dir)), vec![format!("{}.hidden", dir)]);
+}
+
+#[test]
+fn missing_directory_has_no_candidates() {
+    assert!(complete_path("/does/not/exist/").is_empty());
+}
+
+#[test]
+fn common_prefix_of_candidates() {
+    let candidates = vec!["notes.md".to_string(), "notes.txt".to_string()];
+    assert_eq!(common_prefix(&candidates), "notes.");
+    assert_eq!(common_prefix(&["notes".to_string(), "no".to_string()]), "no");
+    assert_eq!(common_prefix(&[]), "");
+}
+
+#[test]
+fn attach_command_is_parsed() {
+    assert_eq!(attach_path("/attach /tmp/cat.jpg"), Some(PathBuf::from("/tmp/cat.jpg")));
+    assert_eq!(attach_path("/attach  "), None);
+    assert_eq!(attach_path("hello /attach x"), None);
+}
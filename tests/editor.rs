@@ -0,0 +1,98 @@
+use signald_tui::editor::InputEditor;
+
+fn editor(text: &str) -> InputEditor {
+    let mut editor = InputEditor::new();
+    for c in text.chars() {
+        editor.insert(c);
+    }
+    editor
+}
+
+#[test]
+fn cursor_moves_over_graphemes() {
+    let mut editor = editor("añe\u{301}");
+    editor.move_left();
+    assert_eq!(editor.cursor(), "añ".len());
+    editor.move_left();
+    editor.move_left();
+    editor.move_left();
+    assert_eq!(editor.cursor(), 0);
+    editor.move_right();
+    editor.move_right();
+    assert_eq!(editor.cursor(), "añ".len());
+}
+
+#[test]
+fn delete_removes_whole_graphemes() {
+    let mut editor = editor("e\u{301}ne\u{301}");
+    editor.delete_backward();
+    assert_eq!(editor.text(), "e\u{301}n");
+    editor.move_line_start();
+    editor.delete_forward();
+    assert_eq!(editor.text(), "n");
+}
+
+#[test]
+fn readline_line_editing() {
+    let mut editor = editor("hello brave new world");
+    editor.delete_word_backward();
+    assert_eq!(editor.text(), "hello brave new ");
+    editor.move_word_left();
+    editor.move_word_left();
+    assert_eq!(editor.cursor(), "hello ".len());
+    editor.kill_to_line_end();
+    assert_eq!(editor.text(), "hello ");
+    editor.move_line_start();
+    editor.move_word_right();
+    assert_eq!(editor.cursor(), "hello".len());
+    editor.kill_to_line_start();
+    assert_eq!(editor.text(), " ");
+}
+
+#[test]
+fn line_movement_stays_in_the_current_line() {
+    let mut editor = editor("first");
+    editor.insert_newline();
+    for c in "second".chars() {
+        editor.insert(c);
+    }
+    editor.move_line_start();
+    assert_eq!(editor.cursor(), "first\n".len());
+    editor.kill_to_line_end();
+    assert_eq!(editor.text(), "first\n");
+}
+
+#[test]
+fn undo_restores_previous_words() {
+    let mut editor = editor("one two");
+    editor.undo();
+    assert_eq!(editor.text(), "one ");
+    editor.undo();
+    assert_eq!(editor.text(), "one");
+    editor.undo();
+    assert_eq!(editor.text(), "");
+}
+
+#[test]
+fn layout_wraps_rows_and_places_cursor() {
+    let mut editor = editor("abcdef");
+    editor.insert_newline();
+    editor.insert('g');
+
+    let (rows, cursor) = editor.layout(4);
+    assert_eq!(rows, vec!["abcd", "ef", "g"]);
+    assert_eq!(cursor, (2, 1));
+
+    editor.move_line_start();
+    editor.move_left();
+    let (_, cursor) = editor.layout(4);
+    assert_eq!(cursor, (1, 2));
+}
+
+#[test]
+fn take_clears_the_editor() {
+    let mut editor = editor("message");
+    assert_eq!(editor.take(), "message");
+    assert!(editor.is_empty());
+    assert_eq!(editor.cursor(), 0);
+}
@@ -0,0 +1,296 @@
+use crossterm::event::{KeyModifiers, MouseButton, MouseEvent};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tui::layout::Rect;
+
+use signald_tui::app::{App, ChatFrame, Contact, Message, View};
+use signald_tui::event::key::Key;
+use signald_tui::handlers::Handler;
+use signald_tui::handlers::accounthandler::AccountHandler;
+use signald_tui::handlers::contacthandler::ContactHandler;
+use signald_tui::handlers::inputhandler::InputHandler;
+use signald_tui::handlers::messagehandler::MessageHandler;
+use signald_tui::handlers::mousehandler::MouseHandler;
+use signald_tui::keymap::Action;
+use signald_tui::network::IoEvent;
+
+use common::{app, message, CONTACT};
+
+mod common;
+
+/// Dispatch a key press like the main loop does, returns the action
+fn press(app: &mut App, key: Key) -> Option<Action> {
+    let action = app.keymap.action(app.focused_view, key)?;
+    match app.focused_view {
+        View::Accounts => AccountHandler::handle(action, app),
+        View::Contacts => ContactHandler::handle(action, app),
+        View::Chat => InputHandler::handle(action, app),
+        View::Messages => MessageHandler::handle(action, app),
+        View::Errors => {}
+    }
+    Some(action)
+}
+
+#[test]
+fn focus_switches_between_contacts_and_chat() {
+    let (mut app, _rx) = app();
+    assert_eq!(app.focused_view, View::Contacts);
+
+    press(&mut app, Key::Char('i'));
+    assert_eq!(app.focused_view, View::Chat);
+
+    press(&mut app, Key::Esc);
+    assert_eq!(app.focused_view, View::Contacts);
+
+    press(&mut app, Key::Enter);
+    assert_eq!(app.focused_view, View::Chat);
+
+    press(&mut app, Key::Tab);
+    assert_eq!(app.focused_view, View::Contacts);
+
+    press(&mut app, Key::Tab);
+    assert_eq!(app.focused_view, View::Chat);
+}
+
+#[test]
+fn typing_in_chat_edits_input() {
+    let (mut app, _rx) = app();
+    app.focused_view = View::Chat;
+
+    for c in "quit".chars() {
+        assert_eq!(press(&mut app, Key::Char(c)), Some(Action::Insert(c)));
+    }
+    assert_eq!(app.input.text(), "quit");
+}
+
+#[test]
+fn q_quits_outside_of_chat() {
+    let (mut app, _rx) = app();
+    assert_eq!(press(&mut app, Key::Char('q')), Some(Action::Quit));
+    assert_eq!(app.keymap.action(View::Chat, Key::Ctrl('c')), Some(Action::Quit));
+}
+
+/// The events sent to the network so far
+fn sent_events(rx: &mut UnboundedReceiver<IoEvent>) -> Vec<IoEvent> {
+    std::iter::from_fn(|| rx.try_recv().ok()).collect()
+}
+
+/// An app whose conversation with Alice contains one received message, and the receiving end of its io channel
+fn app_with_message() -> (App, UnboundedReceiver<IoEvent>) {
+    let (mut app, rx) = app();
+    app.add_message(CONTACT.to_string(), Message {
+        message: "hello".to_string(),
+        ..message(CONTACT, 1000)
+    });
+    app.focused_view = View::Chat;
+    (app, rx)
+}
+
+#[test]
+fn reacting_to_a_selected_message() {
+    let (mut app, mut rx) = app_with_message();
+
+    press(&mut app, Key::Alt('s'));
+    assert_eq!(app.focused_view, View::Messages);
+    press(&mut app, Key::Enter);
+    assert_eq!(app.reaction_picker, Some(0));
+    press(&mut app, Key::Char('l'));
+    press(&mut app, Key::Enter);
+    assert_eq!(app.reaction_picker, None);
+
+    match rx.try_recv() {
+        Ok(IoEvent::SendReaction(data)) => {
+            assert_eq!(data.reaction.emoji, app.config.reactions[1]);
+            assert_eq!(data.reaction.target_timestamp, 1000);
+        }
+        _ => panic!("No reaction sent"),
+    }
+    let message = app.get_selected_message().unwrap();
+    let conv = app.get_current_conversation().unwrap();
+    assert_eq!(conv.reaction_counts(&message).len(), 1);
+
+    // Esc leaves the selection
+    press(&mut app, Key::Esc);
+    assert_eq!(app.focused_view, View::Chat);
+    assert_eq!(app.selected_message, None);
+}
+
+#[test]
+fn replying_to_a_selected_message() {
+    let (mut app, mut rx) = app_with_message();
+
+    press(&mut app, Key::Alt('s'));
+    press(&mut app, Key::Char('r'));
+    assert_eq!(app.focused_view, View::Chat);
+    assert_eq!(app.reply_to.as_ref().unwrap().id, 1000);
+
+    for c in "hi".chars() {
+        press(&mut app, Key::Char(c));
+    }
+    press(&mut app, Key::Enter);
+    assert!(app.reply_to.is_none());
+
+    match rx.try_recv() {
+        Ok(IoEvent::SendMessage(data)) => {
+            assert_eq!(data.message, "hi");
+            let quote = data.quote.unwrap();
+            assert_eq!(quote.author, CONTACT);
+            assert_eq!(quote.text, "hello");
+        }
+        _ => panic!("No message sent"),
+    }
+}
+
+#[test]
+fn replying_with_an_attachment() {
+    let (mut app, mut rx) = app_with_message();
+
+    press(&mut app, Key::Alt('s'));
+    press(&mut app, Key::Char('r'));
+    app.input.set_text("/attach notes.txt".to_string());
+    press(&mut app, Key::Enter);
+    assert!(app.reply_to.is_none());
+
+    let attachment = sent_events(&mut rx).into_iter().find_map(|e| match e {
+        IoEvent::SendAttachment(data) => Some(data),
+        _ => None,
+    });
+    assert_eq!(attachment.unwrap().quote.unwrap().id, 1000);
+}
+
+#[test]
+fn esc_cancels_reply_first() {
+    let (mut app, _rx) = app_with_message();
+
+    press(&mut app, Key::Alt('s'));
+    press(&mut app, Key::Char('r'));
+    press(&mut app, Key::Esc);
+    assert!(app.reply_to.is_none());
+    assert_eq!(app.focused_view, View::Chat);
+
+    press(&mut app, Key::Esc);
+    assert_eq!(app.focused_view, View::Contacts);
+}
+
+#[test]
+fn typing_is_sent_once_and_stopped_when_cleared() {
+    let (mut app, mut rx) = app_with_message();
+
+    for c in "hey".chars() {
+        press(&mut app, Key::Char(c));
+    }
+    let typing: Vec<bool> = sent_events(&mut rx).into_iter()
+        .filter_map(|e| match e {
+            IoEvent::SendTyping(data) => Some(data.started),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(typing, vec![true]);
+
+    press(&mut app, Key::Ctrl('u'));
+    match rx.try_recv() {
+        Ok(IoEvent::SendTyping(data)) => assert!(!data.started),
+        _ => panic!("No typing stopped sent"),
+    }
+}
+
+#[test]
+fn sending_ends_typing_without_stop() {
+    let (mut app, mut rx) = app_with_message();
+
+    press(&mut app, Key::Char('a'));
+    press(&mut app, Key::Enter);
+    let events = sent_events(&mut rx);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(events[1], IoEvent::SendMessage(_)));
+
+    // Typing again starts a new indicator
+    press(&mut app, Key::Char('b'));
+    assert!(matches!(rx.try_recv(), Ok(IoEvent::SendTyping(_))));
+}
+
+/// An app with two conversations and the panes of a 80x24 terminal
+fn app_with_layout() -> App {
+    let (mut app, _rx) = app_with_message();
+    for timestamp in 2000..2010 {
+        app.add_message(CONTACT.to_string(), message(CONTACT, timestamp));
+    }
+    app.sidebar_area = Some(Rect::new(0, 0, 20, 24));
+    app.chat_area = Rect::new(20, 0, 60, 20);
+    app.input_area = Rect::new(20, 20, 60, 4);
+    app.chat_frame = ChatFrame { shown: 4, max_scroll: 7 };
+    app
+}
+
+#[test]
+fn clicking_a_conversation_selects_it() {
+    let mut app = app_with_layout();
+    app.focused_view = View::Chat;
+    let second = app.conversation_list[1].clone();
+
+    MouseHandler::handle(MouseEvent::Down(MouseButton::Left, 5, 2, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.get_selected_conversation_id(), Some(second));
+    assert_eq!(app.focused_view, View::Contacts);
+
+    // The border doesn't select anything
+    MouseHandler::handle(MouseEvent::Down(MouseButton::Left, 5, 0, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.selected_conversation_index, 1);
+
+    MouseHandler::handle(MouseEvent::Down(MouseButton::Left, 30, 22, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.focused_view, View::Chat);
+}
+
+#[test]
+fn clicking_a_scrolled_sidebar_selects_the_conversation_shown() {
+    let (mut app, _rx) = app();
+    app.update_contacts((0..30).map(|i| Contact {
+        number: format!("+324{:08}", i),
+        name: None,
+        color: None,
+    }).collect());
+    // 22 of the 30 conversations fit between the borders
+    app.sidebar_area = Some(Rect::new(0, 0, 20, 24));
+
+    app.select_conversation(25);
+    app.scroll_sidebar(22);
+    assert_eq!(app.sidebar_scroll, 4);
+
+    MouseHandler::handle(MouseEvent::Down(MouseButton::Left, 5, 1, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.selected_conversation_index, 4);
+    app.scroll_sidebar(22);
+    assert_eq!(app.sidebar_scroll, 4);
+
+    MouseHandler::handle(MouseEvent::Down(MouseButton::Left, 5, 22, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.selected_conversation_index, 25);
+
+    // Scrolled back up once the selection moves above the first row shown
+    app.select_conversation(1);
+    app.scroll_sidebar(22);
+    assert_eq!(app.sidebar_scroll, 1);
+}
+
+#[test]
+fn mouse_wheel_scrolls_the_chat() {
+    let mut app = app_with_layout();
+    let index = app.conversation_list.iter().position(|id| id == CONTACT).unwrap();
+    app.select_conversation(index);
+
+    MouseHandler::handle(MouseEvent::ScrollUp(30, 5, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 3);
+
+    MouseHandler::handle(MouseEvent::ScrollDown(30, 5, KeyModifiers::NONE), &mut app);
+    assert_eq!(app.get_current_conversation().unwrap().scroll, 0);
+}
+
+#[test]
+fn hiding_the_sidebar_moves_the_focus() {
+    let (mut app, _rx) = app();
+    assert!(app.sidebar_visible);
+    assert_eq!(app.keymap.action(View::Contacts, Key::F2), Some(Action::ToggleSidebar));
+
+    app.toggle_sidebar();
+    assert!(!app.sidebar_visible);
+    assert_eq!(app.focused_view, View::Chat);
+
+    app.toggle_sidebar();
+    assert!(app.sidebar_visible);
+}
@@ -0,0 +1,48 @@
+use image::{DynamicImage, Rgb, RgbImage};
+
+use signald_tui::ui::image::{encode_kitty, encode_sixel, ImageProtocol, Preview};
+
+#[test]
+fn previews_fit_in_the_size_cap() {
+    let preview = Preview::from_image(DynamicImage::new_rgb8(400, 200), 40, 12);
+    assert_eq!((preview.columns, preview.rows), (40, 10));
+
+    let preview = Preview::from_image(DynamicImage::new_rgb8(100, 400), 40, 12);
+    assert_eq!((preview.columns, preview.rows), (6, 12));
+}
+
+#[test]
+fn small_images_are_not_scaled_up() {
+    let preview = Preview::from_image(DynamicImage::new_rgb8(4, 3), 40, 12);
+    assert_eq!((preview.columns, preview.rows), (4, 2));
+    assert_eq!(preview.encode(ImageProtocol::HalfBlocks), None);
+}
+
+#[test]
+fn half_blocks_have_a_cell_per_two_pixels() {
+    let preview = Preview::from_image(DynamicImage::new_rgb8(5, 4), 40, 12);
+    let lines = preview.half_block_lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|l| l.len() == 5));
+}
+
+#[test]
+fn sixel_image_is_framed() {
+    let image = RgbImage::from_pixel(3, 7, Rgb([255, 0, 0]));
+    let sixel = encode_sixel(&image);
+    assert!(sixel.starts_with("\x1bPq\"1;1;3;7"));
+    assert!(sixel.ends_with("\x1b\\"));
+    // Two bands of six rows, all pixels in the pure red palette entry
+    assert_eq!(sixel.matches('-').count(), 2);
+    assert!(sixel.contains("#180~~~$"));
+}
+
+#[test]
+fn kitty_payload_is_chunked() {
+    let image = DynamicImage::new_rgb8(64, 64);
+    let kitty = encode_kitty(&image, 8, 4);
+    assert!(kitty.starts_with("\x1b_Ga=T,f=32,s=64,v=64,c=8,r=4,"));
+    // Every chunk but the last announces more data
+    assert_eq!(kitty.matches("\x1b_Gm=1;").count(), kitty.matches("\x1b_G").count() - 2);
+    assert_eq!(kitty.matches("\x1b_Gm=0;").count(), 1);
+}
@@ -0,0 +1,48 @@
+use signald_tui::app::View;
+use signald_tui::config::Config;
+use signald_tui::event::key::Key;
+use signald_tui::keymap::{Action, Keymap};
+
+#[test]
+fn keys_are_parsed() {
+    assert_eq!("j".parse::<Key>(), Ok(Key::Char('j')));
+    assert_eq!("Enter".parse::<Key>(), Ok(Key::Enter));
+    assert_eq!("pageup".parse::<Key>(), Ok(Key::PageUp));
+    assert_eq!("Ctrl-w".parse::<Key>(), Ok(Key::Ctrl('w')));
+    assert_eq!("Alt-b".parse::<Key>(), Ok(Key::Alt('b')));
+    assert_eq!("F5".parse::<Key>(), Ok(Key::F5));
+    assert_eq!("Space".parse::<Key>(), Ok(Key::Char(' ')));
+    assert!("Hyper-x".parse::<Key>().is_err());
+}
+
+#[test]
+fn config_bindings_replace_defaults() {
+    let config = Config::parse(r#"
+        [keys.contacts]
+        next-contact = ["n", "Down"]
+        quit = "x"
+
+        [keys.chat]
+        send = "Ctrl-s"
+    "#).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+
+    assert_eq!(keymap.action(View::Contacts, Key::Char('n')), Some(Action::NextContact));
+    assert_eq!(keymap.action(View::Contacts, Key::Down), Some(Action::NextContact));
+    assert_eq!(keymap.action(View::Contacts, Key::Char('j')), None);
+    assert_eq!(keymap.action(View::Contacts, Key::Char('x')), Some(Action::Quit));
+    assert_eq!(keymap.action(View::Contacts, Key::Char('q')), None);
+    assert_eq!(keymap.action(View::Chat, Key::Ctrl('s')), Some(Action::Send));
+    assert_eq!(keymap.action(View::Chat, Key::Enter), None);
+    // Untouched sections keep their defaults
+    assert_eq!(keymap.action(View::Accounts, Key::Char('j')), Some(Action::NextAccount));
+}
+
+#[test]
+fn unknown_actions_are_rejected() {
+    let config = Config::parse(r#"
+        [keys.contacts]
+        launch-rockets = "r"
+    "#).unwrap();
+    assert!(Keymap::from_config(&config.keys).is_err());
+}
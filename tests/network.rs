@@ -0,0 +1,895 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use signald_tui::app::{App, ConnectionState, DeliveryStatus, NoticeKind, Quote, Reaction, View};
+use signald_tui::config::Config;
+use signald_tui::network::{reconnect_delay, IoEvent, LoadPreviewData, Network, SendAttachmentData, SendMessageData, SendReactionData, SendTypingData};
+use signald_tui::notify::{Notification, Notifier};
+use signald_tui::update::StateUpdate;
+
+use common::{tick_until, wait_for_request, MockSignald, TempPath, Ui, CONTACT, OTHER_CONTACT, USERNAME};
+
+mod common;
+
+const OTHER_USERNAME: &str = "+32400000001";
+const GROUP: &str = "Z3JvdXAtaWQ=";
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Records notifications instead of showing them
+#[derive(Clone, Default)]
+struct MockNotifier {
+    shown: Arc<Mutex<Vec<Notification>>>,
+}
+
+impl MockNotifier {
+    fn shown(&self) -> Vec<Notification> {
+        self.shown.lock().unwrap().clone()
+    }
+}
+
+impl Notifier for MockNotifier {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        self.shown.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+fn setup(mock: &MockSignald) -> (Ui, Network) {
+    setup_with_config(mock, Config {
+        data_dir: Some(mock.data_dir()),
+        ..Config::default()
+    })
+}
+
+fn setup_with_config(mock: &MockSignald, config: Config) -> (Ui, Network) {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let (updates_tx, updates_rx) = tokio::sync::mpsc::unbounded_channel::<StateUpdate>();
+    let preferred_account = config.account.clone();
+    let mut network = Network::with_socket_path(updates_tx, mock.socket_path());
+    if let Some(account) = preferred_account {
+        network.set_preferred_account(account);
+    }
+    (Ui::new(App::with_config(tx, config), updates_rx), network)
+}
+
+#[tokio::test]
+async fn load_account_sets_username() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+
+    let app = ui.sync();
+    assert!(app.loaded);
+    assert_eq!(app.username, USERNAME);
+}
+
+#[tokio::test]
+async fn multiple_accounts_show_account_picker() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_account(OTHER_USERNAME)
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    {
+        let app = ui.sync();
+        assert!(!app.loaded);
+        assert!(matches!(app.focused_view, View::Accounts));
+        assert_eq!(app.accounts, vec![USERNAME.to_string(), OTHER_USERNAME.to_string()]);
+    }
+
+    network.handle_event(IoEvent::SelectAccount(OTHER_USERNAME.to_string())).await;
+
+    let app = ui.sync();
+    assert!(app.loaded);
+    assert!(matches!(app.focused_view, View::Contacts));
+    assert_eq!(app.username, OTHER_USERNAME);
+}
+
+#[tokio::test]
+async fn configured_account_is_loaded() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_account(OTHER_USERNAME)
+        .start();
+    let (mut ui, mut network) = setup_with_config(&mock, Config {
+        account: Some(OTHER_USERNAME.to_string()),
+        data_dir: Some(mock.data_dir()),
+        ..Config::default()
+    });
+
+    network.handle_event(IoEvent::LoadAccount).await;
+
+    let app = ui.sync();
+    assert!(app.loaded);
+    assert_eq!(app.username, OTHER_USERNAME);
+}
+
+#[tokio::test]
+async fn no_accounts_does_not_panic() {
+    let mock = MockSignald::new().start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+
+    let app = ui.sync();
+    assert!(!app.loaded);
+    assert!(app.accounts.is_empty());
+}
+
+#[tokio::test]
+async fn contact_list_creates_conversations() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(USERNAME, "")
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::GetContactList).await;
+
+    let app = ui.sync();
+    assert_eq!(app.contacts.len(), 2);
+    assert_eq!(app.contacts[0].name, Some("Me".to_string()));
+    assert_eq!(app.contacts[1].name, Some("Alice".to_string()));
+    assert!(app.conversations.contains_key(CONTACT));
+}
+
+#[tokio::test]
+async fn incoming_message_is_added_to_conversation() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::data_message(USERNAME, CONTACT, 1000, "hello"))
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::GetContactList).await;
+    network.handle_event(IoEvent::Subscribe).await;
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    assert_eq!(conv.messages[0].message, "hello");
+    assert_eq!(conv.messages[0].sender, CONTACT);
+    assert_eq!(conv.messages[0].receiver, USERNAME);
+    assert_eq!(conv.messages[0].timestamp, 1000);
+}
+
+#[tokio::test]
+async fn incoming_attachment_is_kept() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::attachment_message(USERNAME, CONTACT, 1000, "cat.jpg", "/var/lib/signald/attachments/1234567890"))
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string()).map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    let attachment = app.selected_attachment().unwrap();
+    assert_eq!(attachment.filename, "cat.jpg");
+    assert_eq!(attachment.content_type.as_deref(), Some("image/jpeg"));
+    assert_eq!(attachment.size, Some(2048));
+    assert_eq!(attachment.path.as_deref(), Some("/var/lib/signald/attachments/1234567890"));
+}
+
+#[tokio::test]
+async fn incoming_reaction_is_applied() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::data_message(USERNAME, CONTACT, 1000, "hello"))
+        .with_message(MockSignald::reaction_message(USERNAME, CONTACT, 2000, "👍", CONTACT, 1000))
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    let reacted = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string()).map_or(false, |c| !c.reactions.is_empty())
+    }).await;
+    assert!(reacted);
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    // The reaction is not a message of its own
+    assert_eq!(conv.messages.len(), 1);
+    assert_eq!(conv.reaction_counts(&conv.messages[0]), vec![("👍".to_string(), 1)]);
+}
+
+#[tokio::test]
+async fn send_reaction_reaches_signald() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (_ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendReaction(SendReactionData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        reaction: Reaction {
+            sender: USERNAME.to_string(),
+            emoji: "❤️".to_string(),
+            target_author: CONTACT.to_string(),
+            target_timestamp: 1000,
+            remove: false,
+        },
+    })).await;
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["recipientNumber"], CONTACT);
+    assert_eq!(sends[0]["reaction"]["emoji"], "❤️");
+    assert_eq!(sends[0]["reaction"]["targetAuthor"]["number"], CONTACT);
+    assert_eq!(sends[0]["reaction"]["targetSentTimestamp"], 1000);
+}
+
+#[tokio::test]
+async fn incoming_reply_keeps_quote() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::reply_message(USERNAME, CONTACT, 2000, "yes!", USERNAME, 1000, "lunch?"))
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string()).map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+    assert_eq!(message.message, "yes!");
+    assert_eq!(message.quote, Some(Quote {
+        id: 1000,
+        author: USERNAME.to_string(),
+        text: "lunch?".to_string(),
+    }));
+}
+
+#[tokio::test]
+async fn send_reply_reaches_signald() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendMessage(SendMessageData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        message: "sure".to_string(),
+        quote: Some(Quote {
+            id: 1000,
+            author: CONTACT.to_string(),
+            text: "coffee?".to_string(),
+        }),
+    })).await;
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["messageBody"], "sure");
+    assert_eq!(sends[0]["quote"]["id"], 1000);
+    assert_eq!(sends[0]["quote"]["author"]["number"], CONTACT);
+    assert_eq!(sends[0]["quote"]["text"], "coffee?");
+
+    let app = ui.sync();
+    let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+    assert_eq!(message.status, DeliveryStatus::Sent);
+    assert_eq!(message.quote.as_ref().unwrap().text, "coffee?");
+}
+
+#[tokio::test]
+async fn typing_indicator_is_shown_until_message() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::typing_message(USERNAME, CONTACT, "STARTED", None))
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    let typing = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| c.typing_members() == vec![CONTACT])
+    }).await;
+    assert!(typing);
+
+    mock.push(MockSignald::data_message(USERNAME, CONTACT, 1000, "hi"));
+    let stopped = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| c.typing_members().is_empty())
+    }).await;
+    assert!(stopped);
+}
+
+#[tokio::test]
+async fn send_typing_reaches_signald() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (_ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendTyping(SendTypingData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        started: true,
+    })).await;
+    network.handle_event(IoEvent::SendTyping(SendTypingData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        started: false,
+    })).await;
+
+    // The requests are not waited for
+    assert!(wait_for_request(&mock, "typing_started", TIMEOUT).await);
+    assert!(wait_for_request(&mock, "typing_stopped", TIMEOUT).await);
+    assert_eq!(mock.requests_of_type("typing_started")[0]["recipientNumber"], CONTACT);
+}
+
+#[tokio::test]
+async fn sync_message_is_added_to_destination_conversation() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::GetContactList).await;
+    network.handle_event(IoEvent::Subscribe).await;
+    mock.push(MockSignald::sync_message(USERNAME, CONTACT, 2000, "sent elsewhere"));
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    assert_eq!(conv.messages[0].message, "sent elsewhere");
+    assert_eq!(conv.messages[0].sender, USERNAME);
+}
+
+#[tokio::test]
+async fn send_message_reaches_signald() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::GetContactList).await;
+    network.handle_event(IoEvent::SendMessage(SendMessageData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        message: "hi there".to_string(),
+        quote: None,
+    })).await;
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["username"], USERNAME);
+    assert_eq!(sends[0]["recipientNumber"], CONTACT);
+    assert_eq!(sends[0]["messageBody"], "hi there");
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    assert_eq!(conv.messages.len(), 1);
+    assert_eq!(conv.messages[0].message, "hi there");
+}
+
+#[tokio::test]
+async fn send_attachment_reaches_signald() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+    let dir = TempPath::new("signald-tui-attachment");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("image.png");
+    std::fs::write(&path, b"not really a png").unwrap();
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendAttachment(SendAttachmentData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        path: path.clone(),
+        quote: None,
+    })).await;
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["recipientNumber"], CONTACT);
+    assert_eq!(sends[0]["attachments"][0]["filename"], path.to_str().unwrap());
+
+    let app = ui.sync();
+    let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+    assert_eq!(message.status, DeliveryStatus::Sent);
+    assert_eq!(message.timestamp, mock.sent_timestamps()[0]);
+    assert_eq!(message.attachments.len(), 1);
+    assert_eq!(message.attachments[0].content_type.as_deref(), Some("image/png"));
+    assert_eq!(message.attachments[0].size, Some(16));
+}
+
+#[tokio::test]
+async fn missing_attachment_fails() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+    let missing = TempPath::new("signald-tui-missing");
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendAttachment(SendAttachmentData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        path: missing.to_path_buf(),
+        quote: None,
+    })).await;
+
+    assert!(mock.requests_of_type("send").is_empty());
+    let app = ui.sync();
+    let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+    assert_eq!(message.status, DeliveryStatus::Failed);
+    let notice = app.notice.as_ref().unwrap();
+    assert_eq!(notice.kind, NoticeKind::Error);
+    assert!(notice.text.starts_with("Could not read"));
+}
+
+#[tokio::test]
+async fn previews_are_decoded_by_the_network() {
+    let mock = MockSignald::new().start();
+    let (mut ui, mut network) = setup(&mock);
+    let dir = TempPath::new("signald-tui-preview");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("photo.png").display().to_string();
+    image::DynamicImage::new_rgb8(400, 200).save(&path).unwrap();
+    ui.sync().pending_previews.insert(path.clone());
+
+    network.handle_event(IoEvent::LoadPreview(LoadPreviewData {
+        path: path.clone(),
+        max_columns: 40,
+        max_rows: 12,
+    })).await;
+
+    let app = ui.sync();
+    assert!(app.pending_previews.is_empty());
+    let preview = app.image_previews[&path].as_ref().unwrap();
+    assert_eq!((preview.columns, preview.rows), (40, 10));
+}
+
+#[tokio::test]
+async fn history_is_restored_on_restart() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_message(MockSignald::data_message(USERNAME, CONTACT, 1000, "hello"))
+        .start();
+    let config = Config {
+        data_dir: Some(mock.data_dir()),
+        ..Config::default()
+    };
+
+    {
+        let (mut ui, mut network) = setup_with_config(&mock, config.clone());
+        network.handle_event(IoEvent::LoadAccount).await;
+        network.handle_event(IoEvent::SendMessage(SendMessageData {
+            recipient: CONTACT.to_string(),
+            is_group: false,
+            message: "hi".to_string(),
+            quote: None,
+        })).await;
+        let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+            app.get_conversation(CONTACT.to_string())
+                .map_or(false, |c| c.messages.len() == 2)
+        }).await;
+        assert!(received);
+    }
+
+    let (mut ui, mut network) = setup_with_config(&mock, config);
+    network.handle_event(IoEvent::LoadAccount).await;
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    let messages: Vec<&str> = conv.messages.iter().map(|m| m.message.as_str()).collect();
+    assert_eq!(messages, vec!["hi", "hello"]);
+    assert!(!conv.has_more_history);
+}
+
+#[tokio::test]
+async fn groups_become_conversations() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_group(GROUP, "Friends", &[USERNAME, CONTACT])
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+
+    let app = ui.sync();
+    assert_eq!(app.conversation_list, vec![CONTACT.to_string(), GROUP.to_string()]);
+    let conv = app.get_conversation(GROUP.to_string()).unwrap();
+    assert!(conv.is_group());
+    assert_eq!(conv.title(), "Friends");
+}
+
+#[tokio::test]
+async fn group_message_is_routed_to_group() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_group(GROUP, "Friends", &[USERNAME, CONTACT])
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    mock.push(MockSignald::group_message(USERNAME, CONTACT, GROUP, 3000, "hello group"));
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(GROUP.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    assert!(app.get_conversation(CONTACT.to_string()).unwrap().messages.is_empty());
+    let conv = app.get_conversation(GROUP.to_string()).unwrap();
+    assert_eq!(conv.messages[0].message, "hello group");
+    assert_eq!(conv.messages[0].sender, CONTACT);
+}
+
+#[tokio::test]
+async fn send_message_to_group() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_group(GROUP, "Friends", &[USERNAME, CONTACT])
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendMessage(SendMessageData {
+        recipient: GROUP.to_string(),
+        is_group: true,
+        message: "hi all".to_string(),
+        quote: None,
+    })).await;
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["recipientGroupId"], GROUP);
+    assert_eq!(sends[0]["messageBody"], "hi all");
+
+    let app = ui.sync();
+    assert_eq!(app.get_conversation(GROUP.to_string()).unwrap().messages.len(), 1);
+}
+
+#[tokio::test]
+async fn receipts_update_message_status() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    network.handle_event(IoEvent::SendMessage(SendMessageData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        message: "did you get this?".to_string(),
+        quote: None,
+    })).await;
+
+    // Receipts refer to the timestamp signald sent the message with
+    let timestamp = mock.sent_timestamps()[0];
+    {
+        let app = ui.sync();
+        let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+        assert_eq!(message.status, DeliveryStatus::Sent);
+        assert_eq!(message.timestamp, timestamp);
+    }
+
+    mock.push(MockSignald::receipt_message(USERNAME, CONTACT, "READ", &[timestamp]));
+    mock.push(MockSignald::receipt_message(USERNAME, CONTACT, "DELIVERY", &[timestamp]));
+
+    let read = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| c.messages[0].status == DeliveryStatus::Read)
+    }).await;
+    assert!(read);
+
+    // A late delivery receipt doesn't move the status back
+    network.poll().await;
+    let app = ui.sync();
+    assert_eq!(app.get_conversation(CONTACT.to_string()).unwrap().messages[0].status, DeliveryStatus::Read);
+}
+
+#[tokio::test]
+async fn incoming_messages_are_unread_and_move_up() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_contact(OTHER_CONTACT, "Bob")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    assert_eq!(ui.sync().conversation_list, vec![CONTACT.to_string(), OTHER_CONTACT.to_string()]);
+
+    mock.push(MockSignald::data_message(USERNAME, OTHER_CONTACT, 1000, "one"));
+    mock.push(MockSignald::data_message(USERNAME, OTHER_CONTACT, 2000, "two"));
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(OTHER_CONTACT.to_string())
+            .map_or(false, |c| c.messages.len() == 2)
+    }).await;
+    assert!(received);
+
+    let app = ui.sync();
+    assert_eq!(app.conversation_list, vec![OTHER_CONTACT.to_string(), CONTACT.to_string()]);
+    // The selection stays on the same conversation
+    assert_eq!(app.get_selected_conversation_id(), Some(CONTACT.to_string()));
+    assert_eq!(app.get_conversation(OTHER_CONTACT.to_string()).unwrap().unread, 2);
+
+    app.select_conversation(0);
+    assert_eq!(app.get_conversation(OTHER_CONTACT.to_string()).unwrap().unread, 0);
+}
+
+#[tokio::test]
+async fn messages_in_other_conversations_notify() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_contact(OTHER_CONTACT, "Bob")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+    let notifier = MockNotifier::default();
+    ui.sync().set_notifier(Box::new(notifier.clone()));
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    assert_eq!(ui.sync().get_selected_conversation_id(), Some(CONTACT.to_string()));
+
+    // The selected conversation doesn't notify
+    mock.push(MockSignald::data_message(USERNAME, CONTACT, 1000, "seen"));
+    mock.push(MockSignald::data_message(USERNAME, OTHER_CONTACT, 2000, "hello"));
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(OTHER_CONTACT.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+
+    assert_eq!(notifier.shown(), vec![Notification {
+        conversation: OTHER_CONTACT.to_string(),
+        title: "Bob".to_string(),
+        body: "hello".to_string(),
+    }]);
+}
+
+#[tokio::test]
+async fn muted_conversations_do_not_notify() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_contact(OTHER_CONTACT, "Bob")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+    let notifier = MockNotifier::default();
+    ui.sync().set_notifier(Box::new(notifier.clone()));
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    ui.sync().toggle_mute(OTHER_CONTACT);
+
+    mock.push(MockSignald::data_message(USERNAME, OTHER_CONTACT, 1000, "hello"));
+
+    let received = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        app.get_conversation(OTHER_CONTACT.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+    assert!(notifier.shown().is_empty());
+}
+
+#[test]
+fn reconnect_delay_backs_off() {
+    assert_eq!(reconnect_delay(0), Duration::from_secs(1));
+    assert_eq!(reconnect_delay(1), Duration::from_secs(2));
+    assert_eq!(reconnect_delay(3), Duration::from_secs(8));
+    assert_eq!(reconnect_delay(10), Duration::from_secs(60));
+    assert_eq!(reconnect_delay(100), Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn messages_are_queued_until_reconnected() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    assert_eq!(ui.sync().connection, ConnectionState::Connected);
+
+    mock.disconnect();
+    let lost = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        matches!(app.connection, ConnectionState::Disconnected { .. })
+    }).await;
+    assert!(lost);
+
+    network.handle_event(IoEvent::SendMessage(SendMessageData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        message: "are you there?".to_string(),
+        quote: None,
+    })).await;
+    assert!(mock.requests_of_type("send").is_empty());
+    {
+        let app = ui.sync();
+        let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+        assert_eq!(conv.messages[0].status, DeliveryStatus::Sending);
+    }
+
+    // The first attempt to reconnect is after a second
+    let reconnected = tick_until(&mut network, &mut ui, Duration::from_secs(5), |app| {
+        app.connection == ConnectionState::Connected
+    }).await;
+    assert!(reconnected);
+
+    // The account is restored and the queued message is sent
+    assert_eq!(mock.requests_of_type("subscribe").len(), 2);
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["messageBody"], "are you there?");
+
+    let app = ui.sync();
+    let conv = app.get_conversation(CONTACT.to_string()).unwrap();
+    assert_eq!(conv.messages[0].status, DeliveryStatus::Sent);
+}
+
+#[tokio::test]
+async fn attachments_are_queued_until_reconnected() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+    let dir = TempPath::new("signald-tui-attachment");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notes.txt");
+    std::fs::write(&path, b"notes").unwrap();
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    mock.disconnect();
+    let lost = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        matches!(app.connection, ConnectionState::Disconnected { .. })
+    }).await;
+    assert!(lost);
+
+    // Replies apply to attachments too
+    network.handle_event(IoEvent::SendAttachment(SendAttachmentData {
+        recipient: CONTACT.to_string(),
+        is_group: false,
+        path: path.clone(),
+        quote: Some(Quote { id: 1000, author: CONTACT.to_string(), text: "the notes?".to_string() }),
+    })).await;
+    assert!(mock.requests_of_type("send").is_empty());
+    assert_eq!(ui.sync().get_conversation(CONTACT.to_string()).unwrap().messages[0].status, DeliveryStatus::Sending);
+
+    let reconnected = tick_until(&mut network, &mut ui, Duration::from_secs(5), |app| {
+        app.connection == ConnectionState::Connected
+    }).await;
+    assert!(reconnected);
+
+    let sends = mock.requests_of_type("send");
+    assert_eq!(sends.len(), 1);
+    assert_eq!(sends[0]["attachments"][0]["filename"], path.to_str().unwrap());
+    assert_eq!(sends[0]["quote"]["id"], 1000);
+
+    let app = ui.sync();
+    let message = &app.get_conversation(CONTACT.to_string()).unwrap().messages[0];
+    assert_eq!(message.status, DeliveryStatus::Sent);
+    assert_eq!(message.quote.as_ref().unwrap().text, "the notes?");
+}
+
+#[tokio::test]
+async fn reconnecting_keeps_the_view_and_drafts() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .with_contact(OTHER_CONTACT, "Bob")
+        .start();
+    let (mut ui, mut network) = setup(&mock);
+
+    network.handle_event(IoEvent::LoadAccount).await;
+    {
+        let app = ui.sync();
+        assert_eq!(app.get_selected_conversation_id(), Some(CONTACT.to_string()));
+        app.focus(View::Chat);
+
+        // Write a draft which is saved when switching away, then clear it
+        app.input.set_text("see you".to_string());
+        app.select_conversation(1);
+        app.select_conversation(0);
+        assert_eq!(app.input.text(), "see you");
+        app.input.take();
+    }
+
+    mock.disconnect();
+    let lost = tick_until(&mut network, &mut ui, TIMEOUT, |app| {
+        matches!(app.connection, ConnectionState::Disconnected { .. })
+    }).await;
+    assert!(lost);
+    let reconnected = tick_until(&mut network, &mut ui, Duration::from_secs(5), |app| {
+        app.connection == ConnectionState::Connected
+    }).await;
+    assert!(reconnected);
+    assert_eq!(mock.requests_of_type("subscribe").len(), 2);
+    assert_eq!(mock.requests_of_type("list_contacts").len(), 2);
+
+    let app = ui.sync();
+    assert_eq!(app.focused_view, View::Chat);
+    assert_eq!(app.get_selected_conversation_id(), Some(CONTACT.to_string()));
+
+    // The cleared draft stays cleared
+    app.select_conversation(1);
+    app.select_conversation(0);
+    assert!(app.input.is_empty());
+    let drafts = app.with_store("load the drafts", |s| s.load_drafts()).unwrap();
+    assert!(!drafts.contains_key(CONTACT));
+}
+
+#[tokio::test]
+async fn running_network_signals_changes() {
+    let mock = MockSignald::new()
+        .with_account(USERNAME)
+        .with_contact(CONTACT, "Alice")
+        .start();
+    let (mut ui, network) = setup(&mock);
+    let (io_tx, io_rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+
+    // Like the app, the network runs on its own runtime
+    std::thread::spawn(move || {
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(network.run(io_rx));
+    });
+
+    assert!(io_tx.send(IoEvent::LoadAccount).is_ok());
+    assert!(ui.wait_until(TIMEOUT, |app| app.loaded).await);
+
+    // Incoming messages are handled without polling
+    mock.push(MockSignald::data_message(USERNAME, CONTACT, 1000, "hello"));
+    let received = ui.wait_until(TIMEOUT, |app| {
+        app.get_conversation(CONTACT.to_string())
+            .map_or(false, |c| !c.messages.is_empty())
+    }).await;
+    assert!(received);
+}
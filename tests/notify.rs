@@ -0,0 +1,26 @@
+use signald_tui::notify::{Bell, BellNotifier, FallbackNotifier, Notification, Notifier};
+
+use common::FailingNotifier;
+
+mod common;
+
+fn notification() -> Notification {
+    Notification {
+        conversation: "+32411111111".to_string(),
+        title: "Alice".to_string(),
+        body: "hello".to_string(),
+    }
+}
+
+#[test]
+fn bell_is_rung_once_when_notifications_fail() {
+    let bell = Bell::default();
+    let mut notifier = FallbackNotifier {
+        notifiers: vec![Box::new(FailingNotifier), Box::new(BellNotifier { bell: bell.clone() })],
+    };
+
+    assert!(!bell.take());
+    notifier.notify(&notification()).unwrap();
+    assert!(bell.take());
+    assert!(!bell.take());
+}
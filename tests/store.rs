@@ -0,0 +1,94 @@
+use signald_tui::app::{DeliveryStatus, Message};
+use signald_tui::store::MessageStore;
+
+use common::TempPath;
+
+mod common;
+
+fn message(timestamp: i64) -> Message {
+    Message {
+        sender: "+32411111111".to_string(),
+        receiver: "+32400000000".to_string(),
+        timestamp,
+        message: format!("message {}", timestamp),
+        status: DeliveryStatus::Received,
+        quote: None,
+        attachments: Vec::new(),
+    }
+}
+
+#[test]
+fn pages_are_loaded_from_the_end() {
+    let dir = TempPath::new("signald-tui-store");
+    let store = MessageStore::open(Some(dir.to_path_buf()), "+32400000000").unwrap();
+    for i in 0..10 {
+        store.append("+32411111111", &message(i)).unwrap();
+    }
+
+    let latest: Vec<i64> = store.load_page("+32411111111", 0, 4).unwrap()
+        .iter().map(|m| m.timestamp).collect();
+    assert_eq!(latest, vec![6, 7, 8, 9]);
+
+    let older: Vec<i64> = store.load_page("+32411111111", 8, 4).unwrap()
+        .iter().map(|m| m.timestamp).collect();
+    assert_eq!(older, vec![0, 1]);
+}
+
+#[test]
+fn unknown_conversation_is_empty() {
+    let dir = TempPath::new("signald-tui-store");
+    let store = MessageStore::open(Some(dir.to_path_buf()), "+32400000000").unwrap();
+    assert!(store.load_page("+32499999999", 0, 10).unwrap().is_empty());
+}
+
+#[test]
+fn status_updates_are_persisted() {
+    let dir = TempPath::new("signald-tui-store");
+    let store = MessageStore::open(Some(dir.to_path_buf()), "+32400000000").unwrap();
+    for i in 0..3 {
+        store.append("+32411111111", &message(i)).unwrap();
+    }
+
+    store.append_status("+32411111111", "+32411111111", 1, DeliveryStatus::Delivered).unwrap();
+    store.append_status("+32411111111", "+32411111111", 1, DeliveryStatus::Read).unwrap();
+
+    let statuses: Vec<DeliveryStatus> = store.load_page("+32411111111", 0, 3).unwrap()
+        .iter().map(|m| m.status).collect();
+    assert_eq!(statuses, vec![DeliveryStatus::Received, DeliveryStatus::Read, DeliveryStatus::Received]);
+}
+
+#[test]
+fn messages_appended_after_loading_are_paged() {
+    let dir = TempPath::new("signald-tui-store");
+    let store = MessageStore::open(Some(dir.to_path_buf()), "+32400000000").unwrap();
+    for i in 0..3 {
+        store.append("+32411111111", &message(i)).unwrap();
+    }
+    assert_eq!(store.load_page("+32411111111", 0, 10).unwrap().len(), 3);
+
+    store.append("+32411111111", &message(3)).unwrap();
+    store.append_status("+32411111111", "+32411111111", 3, DeliveryStatus::Read).unwrap();
+
+    let latest = store.load_page("+32411111111", 0, 2).unwrap();
+    assert_eq!(latest.iter().map(|m| m.timestamp).collect::<Vec<i64>>(), vec![2, 3]);
+    assert_eq!(latest[1].status, DeliveryStatus::Read);
+}
+
+#[test]
+fn cut_off_lines_are_skipped() {
+    let dir = TempPath::new("signald-tui-store");
+    let store = MessageStore::open(Some(dir.to_path_buf()), "+32400000000").unwrap();
+    for i in 0..2 {
+        store.append("+32411111111", &message(i)).unwrap();
+    }
+
+    // A message cut off by a crash, without a line break
+    let log = dir.join("+32400000000").join("+32411111111.jsonl");
+    let mut contents = std::fs::read(&log).unwrap();
+    contents.extend_from_slice(b"{\"sender\":");
+    std::fs::write(&log, contents).unwrap();
+
+    let messages: Vec<i64> = store.load_page("+32411111111", 0, 10).unwrap()
+        .iter().map(|m| m.timestamp).collect();
+    assert_eq!(messages, vec![0, 1]);
+}
@@ -0,0 +1,63 @@
+use tui::layout::Rect;
+
+use signald_tui::ui::chat::{chat_window, wrap, ChatWindow};
+use signald_tui::ui::reaction_picker_area;
+
+#[test]
+fn short_text_is_not_wrapped() {
+    assert_eq!(wrap("hello world", 20), vec!["hello world"]);
+}
+
+#[test]
+fn text_is_wrapped_at_word_boundaries() {
+    assert_eq!(wrap("the quick brown fox jumps", 10), vec!["the quick", "brown fox", "jumps"]);
+}
+
+#[test]
+fn long_words_are_broken() {
+    assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+}
+
+#[test]
+fn line_breaks_are_kept() {
+    assert_eq!(wrap("one\ntwo", 10), vec!["one", "two"]);
+}
+
+#[test]
+fn wide_characters_take_two_columns() {
+    assert_eq!(wrap("日本語テキスト", 6), vec!["日本語", "テキス", "ト"]);
+}
+
+#[test]
+fn pages_of_multi_line_messages_overlap() {
+    // 30 messages of 3 rows in a pane of 20 rows
+    let window = chat_window(30, 0, 20, |_| 3);
+    assert_eq!(window, ChatWindow { start: 23, end: 30, scroll: 0, max_scroll: 24 });
+
+    // A page up keeps the message at the top in view, at the bottom
+    let shown = window.end - window.start;
+    let page = chat_window(30, shown - 1, 20, |_| 3);
+    assert_eq!(page.end, window.start + 1);
+}
+
+#[test]
+fn scrolling_past_the_top_fills_from_the_first_message() {
+    let window = chat_window(30, 100, 20, |i| if i == 0 { 5 } else { 3 });
+    assert_eq!(window, ChatWindow { start: 0, end: 6, scroll: 24, max_scroll: 24 });
+}
+
+#[test]
+fn few_messages_are_not_scrolled() {
+    let window = chat_window(3, 2, 20, |_| 3);
+    assert_eq!(window, ChatWindow { start: 0, end: 3, scroll: 0, max_scroll: 0 });
+}
+
+#[test]
+fn reaction_picker_stays_inside_the_chat() {
+    let chat = Rect::new(20, 0, 60, 20);
+    assert_eq!(reaction_picker_area(6, chat), Rect::new(21, 17, 26, 3));
+
+    let narrow = Rect::new(20, 0, 10, 20);
+    let area = reaction_picker_area(6, narrow);
+    assert!(area.x + area.width <= narrow.x + narrow.width);
+}
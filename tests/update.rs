@@ -0,0 +1,189 @@
+use std::time::Instant;
+
+use signald_tui::app::{App, ConnectionState, Contact, DeliveryStatus, Group, Message, View};
+use signald_tui::error::Error;
+use signald_tui::network::IoEvent;
+use signald_tui::notify::Notification;
+use signald_tui::update::{Effect, StateUpdate};
+
+use common::{app, message, FailingNotifier, CONTACT, USERNAME};
+
+mod common;
+
+const GROUP: &str = "group-id";
+
+#[test]
+fn loading_an_account_shows_contacts() {
+    let (app, _rx) = app();
+
+    assert!(app.loaded);
+    assert_eq!(app.username, USERNAME);
+    assert_eq!(app.focused_view, View::Contacts);
+    assert!(app.conversations.contains_key(CONTACT));
+}
+
+#[test]
+fn loading_an_account_leaves_the_store_to_the_caller() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::new(tx);
+    let effects = app.apply(StateUpdate::AccountLoaded(USERNAME.to_string()));
+
+    assert!(matches!(effects[..], [Effect::OpenStore]));
+    assert!(app.store.is_none());
+
+    let effects = app.apply(StateUpdate::ContactsLoaded(vec![Contact {
+        number: CONTACT.to_string(),
+        name: None,
+        color: None,
+    }]));
+    assert!(matches!(&effects[0], Effect::LoadHistory(id) if id == CONTACT));
+}
+
+#[test]
+fn several_accounts_open_the_picker() {
+    let (tx, _rx) = tokio::sync::mpsc::unbounded_channel::<IoEvent>();
+    let mut app = App::new(tx);
+    app.apply(StateUpdate::AccountsLoaded(vec![USERNAME.to_string(), CONTACT.to_string()]));
+
+    assert_eq!(app.focused_view, View::Accounts);
+    assert_eq!(app.accounts.len(), 2);
+}
+
+#[test]
+fn queued_messages_are_updated_by_receipts() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::MessageQueued {
+        conversation: CONTACT.to_string(),
+        message: Message { status: DeliveryStatus::Sending, ..message(USERNAME, 10) },
+    });
+    let effects = app.apply(StateUpdate::MessageStatusChanged { timestamp: 10, status: DeliveryStatus::Delivered });
+
+    let messages = &app.conversations[CONTACT].messages;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].status, DeliveryStatus::Delivered);
+    assert!(matches!(effects[..], [Effect::SaveStatus { timestamp: 10, status: DeliveryStatus::Delivered, .. }]));
+
+    // A status never moves backwards, so there is nothing to save
+    let effects = app.apply(StateUpdate::MessageStatusChanged { timestamp: 10, status: DeliveryStatus::Sent });
+    assert!(effects.is_empty());
+}
+
+#[test]
+fn received_messages_are_added_to_the_conversation() {
+    let (mut app, _rx) = app();
+    let effects = app.apply(StateUpdate::MessageReceived {
+        conversation: CONTACT.to_string(),
+        message: message(CONTACT, 20),
+    });
+
+    assert_eq!(app.conversations[CONTACT].messages[0].message, "message 20");
+    assert!(matches!(&effects[0], Effect::SaveMessage { conversation, .. } if conversation == CONTACT));
+    // The conversation is selected, so it doesn't notify
+    assert!(!effects.iter().any(|e| matches!(e, Effect::Notify(_))));
+}
+
+#[test]
+fn messages_in_other_conversations_are_notified() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::GroupsLoaded(vec![Group {
+        id: GROUP.to_string(),
+        name: Some("Friends".to_string()),
+        members: vec![USERNAME.to_string(), CONTACT.to_string()],
+    }]));
+
+    let effects = app.apply(StateUpdate::MessageReceived {
+        conversation: GROUP.to_string(),
+        message: Message { receiver: GROUP.to_string(), ..message(CONTACT, 30) },
+    });
+
+    let notification = effects.iter().find_map(|e| match e {
+        Effect::Notify(n) => Some(n),
+        _ => None,
+    });
+    assert_eq!(notification.unwrap().title, "Alice in Friends");
+}
+
+#[test]
+fn group_info_only_changes_given_fields() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::GroupsLoaded(vec![Group {
+        id: GROUP.to_string(),
+        name: Some("Friends".to_string()),
+        members: vec![USERNAME.to_string(), CONTACT.to_string()],
+    }]));
+
+    app.apply(StateUpdate::GroupInfoReceived {
+        id: GROUP.to_string(),
+        name: Some("Family".to_string()),
+        members: None,
+    });
+
+    let group = app.groups.iter().find(|g| g.id == GROUP).unwrap();
+    assert_eq!(group.name.as_deref(), Some("Family"));
+    assert_eq!(group.members.len(), 2);
+}
+
+#[test]
+fn group_info_of_unknown_group_creates_it() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::GroupInfoReceived {
+        id: GROUP.to_string(),
+        name: None,
+        members: None,
+    });
+
+    assert!(app.groups.iter().any(|g| g.id == GROUP));
+    assert!(app.conversations[GROUP].is_group());
+}
+
+#[test]
+fn typing_of_own_account_is_ignored() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::TypingChanged {
+        conversation: CONTACT.to_string(),
+        sender: USERNAME.to_string(),
+        started: true,
+    });
+    assert!(app.conversations[CONTACT].typing.is_empty());
+
+    app.apply(StateUpdate::TypingChanged {
+        conversation: CONTACT.to_string(),
+        sender: CONTACT.to_string(),
+        started: true,
+    });
+    assert!(app.conversations[CONTACT].typing.contains_key(CONTACT));
+}
+
+#[test]
+fn failed_notifications_are_reported() {
+    let (mut app, _rx) = app();
+    app.set_notifier(Box::new(FailingNotifier));
+
+    app.perform(vec![Effect::Notify(Notification {
+        conversation: CONTACT.to_string(),
+        title: "Alice".to_string(),
+        body: "hello".to_string(),
+    })]);
+
+    assert_eq!(app.error_log.len(), 1);
+    assert!(app.error_log[0].message.starts_with("Could not show a notification"));
+}
+
+#[test]
+fn failed_sends_name_the_conversation() {
+    let (mut app, _rx) = app();
+    app.apply(StateUpdate::SendFailed { recipient: CONTACT.to_string(), what: "reaction" });
+    app.apply(StateUpdate::Failed(Error::MalformedResponse("the message")));
+
+    assert_eq!(app.error_log.len(), 2);
+    assert_eq!(app.error_log[0].message, "Could not send reaction to Alice");
+}
+
+#[test]
+fn connection_changes_are_applied() {
+    let (mut app, _rx) = app();
+    let retry_at = Instant::now();
+    app.apply(StateUpdate::ConnectionChanged(ConnectionState::Disconnected { retry_at }));
+
+    assert_eq!(app.connection, ConnectionState::Disconnected { retry_at });
+}